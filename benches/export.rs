@@ -0,0 +1,80 @@
+//! Export of 5k counter series to a no-op `MetricService`.
+//!
+//! cargo bench --bench export
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use google_cloud_monitoring_v3::client::MetricService;
+use opentelemetry::{KeyValue, metrics::MeterProvider};
+use opentelemetry_gcloud_monitoring_exporter::GCPMetricsExporter;
+use opentelemetry_sdk::{
+    Resource,
+    metrics::{Instrument, PeriodicReader, SdkMeterProvider, Stream},
+};
+
+const SERIES: usize = 5_000;
+
+#[derive(Debug)]
+struct NoopMetricService;
+
+impl google_cloud_monitoring_v3::stub::MetricService for NoopMetricService {
+    async fn create_metric_descriptor(
+        &self,
+        req: google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> google_cloud_monitoring_v3::Result<
+        google_cloud_gax::response::Response<google_cloud_api::model::MetricDescriptor>,
+    > {
+        Ok(google_cloud_gax::response::Response::from(
+            req.metric_descriptor.unwrap_or_default(),
+        ))
+    }
+
+    async fn create_time_series(
+        &self,
+        _req: google_cloud_monitoring_v3::model::CreateTimeSeriesRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> google_cloud_monitoring_v3::Result<google_cloud_gax::response::Response<()>> {
+        Ok(google_cloud_gax::response::Response::from(()))
+    }
+}
+
+fn export_benchmark(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let exporter = runtime
+        .block_on(
+            GCPMetricsExporter::builder()
+                .with_metric_service(MetricService::from_stub(NoopMetricService))
+                .with_project_id("bench-project")
+                .build(),
+        )
+        .unwrap();
+    let metrics_provider = SdkMeterProvider::builder()
+        .with_resource(
+            Resource::builder_empty()
+                .with_attributes(vec![
+                    KeyValue::new("service.name", "bench"),
+                    KeyValue::new("service.namespace", "exporter"),
+                    KeyValue::new("service.instance.id", "bench-1"),
+                    KeyValue::new("cloud.region", "europe-west1"),
+                ])
+                .build(),
+        )
+        .with_reader(PeriodicReader::builder(exporter).build())
+        // the default cardinality limit is 2000 series
+        .with_view(|_: &Instrument| Stream::builder().with_cardinality_limit(2 * SERIES).build().ok())
+        .build();
+    let counter = metrics_provider.meter("bench").u64_counter("requests").build();
+    for i in 0..SERIES {
+        counter.add(1, &[KeyValue::new("series", i as i64), KeyValue::new("method", "GET")]);
+    }
+    // creates the metric descriptor
+    metrics_provider.force_flush().unwrap();
+
+    c.bench_function("export_5k_series", |b| {
+        b.iter(|| metrics_provider.force_flush().unwrap())
+    });
+    metrics_provider.shutdown().unwrap();
+}
+
+criterion_group!(benches, export_benchmark);
+criterion_main!(benches);
@@ -21,17 +21,19 @@ async fn init_metrics() -> Result<SdkMeterProvider, Box<dyn std::error::Error>>
     // std::env::set_var("GOOGLE_APPLICATION_CREDENTIALS", "/Users/serhiiyatsina/projects/cybx/opentelemetry/opentelemetry-rust-exporter-gcp-cm/.secrets/977645940426-compute@developer.gserviceaccount.com.json");
     std::env::set_var("GOOGLE_APPLICATION_CREDENTIALS", "/Users/serhiiyatsina/projects/cybx/opentelemetry/opentelemetry-rust-exporter-gcp-cm/.secrets/no-access-to-metrics@cybx-chat.iam.gserviceaccount.com.json");
 
-    let mut cfg = GCPMetricsExporterConfig::default();
-    cfg.prefix = "custom.googleapis.com/opencensus/cybx.io/test_service".to_string();
-    cfg.custom_monitored_resource_data = Some(
-        // https://cloud.google.com/monitoring/api/resources#tag_global
-        MonitoredResourceDataConfig {
-            r#type: "global".to_string(),
-            labels: to_labels(json!({
-                "project_id": "cybx-chat",
-            })),
-        },
-    );
+    let cfg = GCPMetricsExporterConfig {
+        prefix: "custom.googleapis.com/opencensus/cybx.io/test_service".to_string(),
+        custom_monitored_resource_data: Some(
+            // https://cloud.google.com/monitoring/api/resources#tag_global
+            MonitoredResourceDataConfig {
+                r#type: "global".to_string(),
+                labels: to_labels(json!({
+                    "project_id": "cybx-chat",
+                })),
+            },
+        ),
+        ..Default::default()
+    };
     let exporter = GCPMetricsExporter::init(cfg).await?;
     // https://github.com/open-telemetry/opentelemetry-rust/blob/main/opentelemetry-sdk/CHANGELOG.md#0280
     let reader = periodic_reader_with_async_runtime::PeriodicReader::builder(exporter, runtime::Tokio)
@@ -0,0 +1,162 @@
+//! Lists, diffs, deletes and creates Cloud Monitoring metric descriptors.
+//!
+//! cargo run --features cli --bin gcp-metric-descriptors -- --project my-project list
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use google_cloud_api::model::MetricDescriptor;
+use opentelemetry_gcloud_monitoring_exporter::{
+    CredentialsConfig, DryRunConfig, DryRunOutput, GCPMetricsExporterConfig, MetricDescriptorAdmin, MetricSchema,
+    diff_metric_descriptors, read_metric_descriptors, validate_prune_prefix,
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "Manage the Cloud Monitoring metric descriptors of an app")]
+struct Args {
+    /// Project of the descriptors, detected like the exporter does if not set.
+    #[arg(long, global = true)]
+    project: Option<String>,
+    /// Sends to this endpoint instead of https://monitoring.googleapis.com.
+    #[arg(long, global = true)]
+    endpoint: Option<String>,
+    /// Allows a plain http:// endpoint, e.g. a local stand-in. TLS is still
+    /// verified for https:// endpoints.
+    #[arg(long, global = true)]
+    insecure: bool,
+    /// Sends no credentials.
+    #[arg(long, global = true, conflicts_with = "credentials_file")]
+    anonymous: bool,
+    /// Service account key JSON file instead of the application default credentials.
+    #[arg(long, global = true)]
+    credentials_file: Option<PathBuf>,
+    /// Writes the create and delete requests to stderr instead of sending them.
+    #[arg(long, global = true)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Prints the descriptors under the prefix as a JSON array.
+    List {
+        #[arg(long, default_value = "workload.googleapis.com/")]
+        prefix: String,
+    },
+    /// Prints the missing, obsolete and changed descriptors under the prefix.
+    Diff {
+        #[arg(long, default_value = "workload.googleapis.com/")]
+        prefix: String,
+        /// Descriptors of the app: the output of `list` or the dry-run output of the exporter.
+        #[arg(long, required_unless_present = "schema", conflicts_with = "schema")]
+        instruments: Option<PathBuf>,
+        /// Metric schema of the app, see `MetricSchema`.
+        #[arg(long)]
+        schema: Option<PathBuf>,
+    },
+    /// Prints the descriptors under the prefix the app does not use anymore,
+    /// and deletes them with `--apply`. Their time series are deleted too.
+    Prune {
+        /// Prefix of the metric types of the app, e.g. `workload.googleapis.com/checkout.`,
+        /// no default: `workload.googleapis.com/` alone holds the metrics of every app.
+        #[arg(long)]
+        prefix: String,
+        /// Descriptors of the app: the output of `list` or the dry-run output of the exporter.
+        #[arg(long, required_unless_present = "schema", conflicts_with = "schema")]
+        instruments: Option<PathBuf>,
+        /// Metric schema of the app, see `MetricSchema`.
+        #[arg(long)]
+        schema: Option<PathBuf>,
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Creates the descriptors of a file.
+    Create {
+        /// Metric prefix of the schema metrics without a `type`.
+        #[arg(long, default_value = "workload.googleapis.com/")]
+        prefix: String,
+        /// The output of `list` or the dry-run output of the exporter.
+        #[arg(long, required_unless_present = "schema", conflicts_with = "schema")]
+        file: Option<PathBuf>,
+        /// Metric schema of the app, see `MetricSchema`.
+        #[arg(long)]
+        schema: Option<PathBuf>,
+    },
+}
+
+impl Args {
+    fn exporter_config(&self) -> GCPMetricsExporterConfig {
+        let credentials = match (&self.credentials_file, self.anonymous) {
+            (_, true) => CredentialsConfig::Anonymous,
+            (Some(path), false) => CredentialsConfig::ServiceAccountKeyFile(path.clone()),
+            (None, false) => CredentialsConfig::ApplicationDefault,
+        };
+        GCPMetricsExporterConfig {
+            project_id: self.project.clone(),
+            endpoint: self.endpoint.clone(),
+            insecure: self.insecure,
+            credentials,
+            dry_run: self.dry_run.then(|| DryRunConfig {
+                output: DryRunOutput::writer(std::io::stderr()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// Descriptors of a descriptors file, or else of a schema file.
+fn read_wanted(
+    file: Option<PathBuf>,
+    schema: Option<PathBuf>,
+    prefix: &str,
+) -> Result<Vec<MetricDescriptor>, Box<dyn std::error::Error>> {
+    match (file, schema) {
+        (Some(file), _) => Ok(read_metric_descriptors(&std::fs::read(file)?)?),
+        (None, Some(schema)) => Ok(MetricSchema::from_file(schema)?.metric_descriptors(prefix.trim_end_matches('/'))),
+        (None, None) => Err("a descriptors or schema file is required".into()),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let admin = MetricDescriptorAdmin::from_config(&args.exporter_config()).await?;
+    match args.command {
+        Command::List { prefix } => {
+            let descriptors = admin.list(&prefix).await?;
+            println!("{}", serde_json::to_string_pretty(&descriptors)?);
+        }
+        Command::Diff {
+            prefix,
+            instruments,
+            schema,
+        } => {
+            let wanted = read_wanted(instruments, schema, &prefix)?;
+            let diff = diff_metric_descriptors(&admin.list(&prefix).await?, &wanted);
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        }
+        Command::Prune {
+            prefix,
+            instruments,
+            schema,
+            apply,
+        } => {
+            validate_prune_prefix(&prefix)?;
+            let wanted = read_wanted(instruments, schema, &prefix)?;
+            let obsolete = diff_metric_descriptors(&admin.list(&prefix).await?, &wanted).obsolete;
+            println!("{}", serde_json::to_string_pretty(&obsolete)?);
+            if apply {
+                admin.delete(&obsolete).await?;
+            } else if !obsolete.is_empty() {
+                eprintln!("run with --apply to delete them");
+            }
+        }
+        Command::Create { prefix, file, schema } => {
+            let descriptors = read_wanted(file, schema, &prefix)?;
+            admin.create(&descriptors).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,435 @@
+use std::sync::Arc;
+
+use google_cloud_monitoring_v3::client::MetricService;
+use opentelemetry_sdk::metrics::Temporality;
+
+#[cfg(feature = "spool")]
+use super::SpoolConfig;
+use super::{
+    CredentialsConfig, DryRunConfig, ExportErrorHandler, GCPMetricsExporter, GCPMetricsExporterConfig,
+    GCPMetricsExporterInitError, LabelDescriptorConfig, MetricSchema, MonitoredResourceDataConfig,
+    MonitoredResourceMapper, MonitoredResourceResolver, ProjectRouting, PrometheusConfig, U64Policy,
+    UniqueIdentifierSource,
+    client::{build_client, validate_credentials, validate_endpoint},
+    dry_run::DryRunMetricService,
+    label_descriptor::validate_label_descriptors,
+    monitored_resource::{check_monitored_resource_descriptors, validate_monitored_resources},
+    project_id::{ProjectIdDetectionConfig, ProjectIdSource, resolve_project_id},
+    unique_identifier::validate_unique_identifier,
+};
+
+/// Builder for [`GCPMetricsExporter`], see [`GCPMetricsExporter::builder`].
+///
+/// Unlike [`GCPMetricsExporter::init`] it accepts a pre-built [`MetricService`]
+/// client, e.g. with custom credentials or endpoint.
+#[derive(Debug, Default)]
+pub struct GCPMetricsExporterBuilder {
+    metric_service: Option<MetricService>,
+    config: GCPMetricsExporterConfig,
+}
+
+impl GCPMetricsExporterBuilder {
+    /// Uses `metric_service` instead of building a client with the default
+    /// credentials. The options of the built client, the endpoint,
+    /// credentials, impersonated service account, quota project, insecure
+    /// and dry-run options, can not be set then.
+    pub fn with_metric_service(mut self, metric_service: MetricService) -> Self {
+        self.metric_service = Some(metric_service);
+        self
+    }
+
+    /// Replaces every option with the ones of `config`.
+    pub fn with_config(mut self, config: GCPMetricsExporterConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_project_id(mut self, project_id: impl Into<String>) -> Self {
+        self.config.project_id = Some(project_id.into());
+        self
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.config.prefix = prefix.into();
+        self
+    }
+
+    pub fn with_unique_identifier(mut self, add_unique_identifier: bool) -> Self {
+        self.config.add_unique_identifier = add_unique_identifier;
+        self
+    }
+
+    /// Writes the unique identifier as the label `key`, `opentelemetry_id` by default.
+    pub fn with_unique_identifier_key(mut self, key: impl Into<String>) -> Self {
+        self.config.unique_identifier_key = key.into();
+        self
+    }
+
+    /// Takes the value of the unique identifier from `source`, see [`UniqueIdentifierSource`].
+    pub fn with_unique_identifier_source(mut self, source: UniqueIdentifierSource) -> Self {
+        self.config.unique_identifier_source = source;
+        self
+    }
+
+    pub fn with_custom_monitored_resource_data(mut self, data: MonitoredResourceDataConfig) -> Self {
+        self.config.custom_monitored_resource_data = Some(data);
+        self
+    }
+
+    /// Maps the OpenTelemetry resource with `mapper` instead of the GCP
+    /// resource detector mapping, e.g. [`GenericNodeMapper`] for on-prem hosts.
+    pub fn with_monitored_resource_mapper(mut self, mapper: impl MonitoredResourceMapper + 'static) -> Self {
+        self.config.monitored_resource_mapper = Arc::new(mapper);
+        self
+    }
+
+    /// Checks the configured monitored resources against the monitored
+    /// resource descriptors of the project when building the exporter.
+    pub fn with_monitored_resource_descriptor_check(mut self, check: bool) -> Self {
+        self.config.check_monitored_resource_descriptors = check;
+        self
+    }
+
+    /// Adds a per metric monitored resource, see [`MonitoredResourceResolver`].
+    pub fn with_monitored_resource_resolver(mut self, resolver: MonitoredResourceResolver) -> Self {
+        self.config.monitored_resource_resolvers.push(resolver);
+        self
+    }
+
+    #[cfg(feature = "spool")]
+    pub fn with_spool(mut self, spool: SpoolConfig) -> Self {
+        self.config.spool = Some(spool);
+        self
+    }
+
+    pub fn with_self_metrics_meter(mut self, meter: opentelemetry::metrics::Meter) -> Self {
+        self.config.self_metrics_meter = Some(meter);
+        self
+    }
+
+    pub fn with_on_error(mut self, on_error: ExportErrorHandler) -> Self {
+        self.config.on_error = Some(on_error);
+        self
+    }
+
+    /// Sends to `endpoint` instead of `https://monitoring.googleapis.com`,
+    /// a plain `http://` endpoint also needs [`GCPMetricsExporterBuilder::with_insecure`].
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.config.endpoint = Some(endpoint.into());
+        self
+    }
+
+    pub fn with_credentials(mut self, credentials: CredentialsConfig) -> Self {
+        self.config.credentials = credentials;
+        self
+    }
+
+    /// Impersonates `service_account` with the configured credentials.
+    pub fn with_impersonated_service_account(mut self, service_account: impl Into<String>) -> Self {
+        self.config.impersonate_service_account = Some(service_account.into());
+        self
+    }
+
+    /// Bills the API calls to `quota_project_id`.
+    pub fn with_quota_project_id(mut self, quota_project_id: impl Into<String>) -> Self {
+        self.config.quota_project_id = Some(quota_project_id.into());
+        self
+    }
+
+    pub fn with_project_id_detection(mut self, project_id_detection: ProjectIdDetectionConfig) -> Self {
+        self.config.project_id_detection = project_id_detection;
+        self
+    }
+
+    /// Writes each time series to the project picked by `project_routing`,
+    /// see [`ProjectRouting`].
+    pub fn with_project_routing(mut self, project_routing: ProjectRouting) -> Self {
+        self.config.project_routing = project_routing;
+        self
+    }
+
+    /// Writes metrics like Managed Service for Prometheus, see [`PrometheusConfig`].
+    pub fn with_prometheus(mut self, prometheus: PrometheusConfig) -> Self {
+        self.config.prometheus = Some(prometheus);
+        self
+    }
+
+    /// Lets Cloud Monitoring create the metric descriptors on the first write,
+    /// see [`GCPMetricsExporterConfig::skip_metric_descriptor_creation`].
+    pub fn with_skip_metric_descriptor_creation(mut self, skip: bool) -> Self {
+        self.config.skip_metric_descriptor_creation = skip;
+        self
+    }
+
+    /// Starts a new interval when a cumulative time series is reset, see
+    /// [`GCPMetricsExporterConfig::handle_cumulative_resets`].
+    pub fn with_handle_cumulative_resets(mut self, handle: bool) -> Self {
+        self.config.handle_cumulative_resets = handle;
+        self
+    }
+
+    /// Writes monotonic counters as GAUGE metrics, see
+    /// [`GCPMetricsExporterConfig::counters_as_gauge`].
+    pub fn with_counters_as_gauge(mut self, counters_as_gauge: bool) -> Self {
+        self.config.counters_as_gauge = counters_as_gauge;
+        self
+    }
+
+    /// Writes UpDownCounters as CUMULATIVE metrics, see
+    /// [`GCPMetricsExporterConfig::up_down_counters_as_cumulative`].
+    pub fn with_up_down_counters_as_cumulative(mut self, up_down_counters_as_cumulative: bool) -> Self {
+        self.config.up_down_counters_as_cumulative = up_down_counters_as_cumulative;
+        self
+    }
+
+    /// Writes u64 sums and gauges as set in `u64_policy`, see [`U64Policy`].
+    pub fn with_u64_policy(mut self, u64_policy: U64Policy) -> Self {
+        self.config.u64_policy = u64_policy;
+        self
+    }
+
+    /// Temporality the SDK aggregates the metrics with, Cumulative by default.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.config.temporality = temporality;
+        self
+    }
+
+    /// Adds the description and value type of a metric label, see [`LabelDescriptorConfig`].
+    pub fn with_label_descriptor(mut self, label_descriptor: LabelDescriptorConfig) -> Self {
+        self.config.label_descriptors.push(label_descriptor);
+        self
+    }
+
+    /// Uses the declared metrics of `schema`, see [`MetricSchema`].
+    pub fn with_schema(mut self, schema: MetricSchema) -> Self {
+        self.config.schema = Some(schema);
+        self
+    }
+
+    /// Accepts a plain `http://` endpoint, TLS is still verified for
+    /// `https://` endpoints.
+    pub fn with_insecure(mut self, insecure: bool) -> Self {
+        self.config.insecure = insecure;
+        self
+    }
+
+    /// Writes the requests to `dry_run.output` instead of sending them,
+    /// see [`DryRunConfig`].
+    pub fn with_dry_run(mut self, dry_run: DryRunConfig) -> Self {
+        self.config.dry_run = Some(dry_run);
+        self
+    }
+
+    /// Validates the options, builds the client if none was given and
+    /// detects the project id if none was configured, see
+    /// [`ProjectIdDetectionConfig`].
+    pub async fn build(self) -> Result<GCPMetricsExporter, GCPMetricsExporterInitError> {
+        validate_config(&self.config)?;
+        if self.metric_service.is_some() {
+            validate_metric_service_config(&self.config)?;
+        }
+
+        let (client, project_id, project_id_source) = connect(self.metric_service, &self.config).await?;
+        tracing::debug!(
+            "GCPMetricsExporter: using project id {} from {}",
+            project_id,
+            project_id_source
+        );
+        if self.config.check_monitored_resource_descriptors && self.config.dry_run.is_none() {
+            check_monitored_resource_descriptors(&client, &project_id, &self.config).await?;
+        }
+
+        let mut exporter = GCPMetricsExporter::new(client, project_id, self.config);
+        exporter.project_id_source = project_id_source;
+        Ok(exporter)
+    }
+}
+
+/// Client and project id for `config`, the client is built unless
+/// `metric_service` is given.
+pub(crate) async fn connect(
+    metric_service: Option<MetricService>,
+    config: &GCPMetricsExporterConfig,
+) -> Result<(MetricService, String, ProjectIdSource), GCPMetricsExporterInitError> {
+    // dry-run mode does not load credentials
+    let service_account_key = match config.dry_run {
+        Some(_) => None,
+        None => config.credentials.service_account_key()?,
+    };
+
+    let client = match (metric_service, config.dry_run.clone()) {
+        (Some(client), _) => client,
+        (None, Some(dry_run)) => MetricService::from_stub(
+            DryRunMetricService::new(dry_run).map_err(GCPMetricsExporterInitError::DryRunOutput)?,
+        ),
+        (None, None) => {
+            // the HTTP client only runs on tokio
+            #[cfg(feature = "tokio")]
+            if tokio::runtime::Handle::try_current().is_err() {
+                return Err(GCPMetricsExporterInitError::InvalidConfig(
+                    "the Cloud Monitoring client needs a tokio runtime, build the exporter inside one".to_string(),
+                ));
+            }
+            build_client(config, service_account_key.clone()).await?
+        }
+    };
+
+    let (project_id, project_id_source) = resolve_project_id(config, service_account_key.as_ref()).await?;
+    Ok((client, project_id, project_id_source))
+}
+
+pub(crate) fn validate_config(config: &GCPMetricsExporterConfig) -> Result<(), GCPMetricsExporterInitError> {
+    let invalid = |msg: String| Err(GCPMetricsExporterInitError::InvalidConfig(msg));
+    if config.prefix.is_empty() || config.prefix.ends_with('/') {
+        return invalid(format!(
+            "prefix must not be empty or end with '/', got {:?}",
+            config.prefix
+        ));
+    }
+    validate_endpoint(config).map_err(GCPMetricsExporterInitError::InvalidConfig)?;
+    validate_credentials(config).map_err(GCPMetricsExporterInitError::InvalidConfig)?;
+    if config.project_id.as_deref() == Some("") {
+        return invalid("project_id must not be empty".to_string());
+    }
+    if let Some(data) = &config.custom_monitored_resource_data {
+        if let (Some(project_id), Some(resource_project_id)) = (&config.project_id, data.labels.get("project_id")) {
+            if project_id != resource_project_id {
+                // writing to a resource of another project is allowed, but usually a mistake
+                tracing::warn!(
+                    "GCPMetricsExporter: project_id {:?} does not match the project_id label {:?} of custom_monitored_resource_data",
+                    project_id,
+                    resource_project_id
+                );
+            }
+        }
+    }
+    if config.prometheus.is_some() {
+        if let Some(data) = &config.custom_monitored_resource_data {
+            if data.r#type != "prometheus_target" {
+                return invalid(format!(
+                    "custom_monitored_resource_data must be a prometheus_target with prometheus, got {:?}",
+                    data.r#type
+                ));
+            }
+        }
+    }
+    validate_monitored_resources(config).map_err(GCPMetricsExporterInitError::InvalidConfig)?;
+    for resolver in &config.monitored_resource_resolvers {
+        if let MonitoredResourceResolver::Attributes { attribute_labels, .. } = resolver {
+            if attribute_labels.is_empty() {
+                return invalid(format!("attribute_labels must not be empty in {:?}", resolver));
+            }
+        }
+    }
+    validate_unique_identifier(config).map_err(GCPMetricsExporterInitError::InvalidConfig)?;
+    validate_label_descriptors(&config.label_descriptors).map_err(GCPMetricsExporterInitError::InvalidConfig)?;
+    config
+        .u64_policy
+        .validate()
+        .map_err(GCPMetricsExporterInitError::InvalidConfig)?;
+    if let Some(schema) = &config.schema {
+        schema.validate().map_err(GCPMetricsExporterInitError::InvalidConfig)?;
+    }
+    #[cfg(feature = "spool")]
+    if let Some(spool) = &config.spool {
+        if spool.directory.as_os_str().is_empty() {
+            return invalid("spool.directory must not be empty".to_string());
+        }
+        if spool.max_size_bytes == 0 {
+            return invalid("spool.max_size_bytes must be greater than 0".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Rejects the options of the client built by the exporter, they would be
+/// ignored with a pre-built metric service.
+pub(crate) fn validate_metric_service_config(
+    config: &GCPMetricsExporterConfig,
+) -> Result<(), GCPMetricsExporterInitError> {
+    let client_options = [
+        ("dry_run", config.dry_run.is_some()),
+        ("endpoint", config.endpoint.is_some()),
+        (
+            "credentials",
+            config.credentials != CredentialsConfig::ApplicationDefault,
+        ),
+        (
+            "impersonate_service_account",
+            config.impersonate_service_account.is_some(),
+        ),
+        ("quota_project_id", config.quota_project_id.is_some()),
+        ("insecure", config.insecure),
+    ];
+    match client_options.iter().find(|(_, set)| *set) {
+        Some((option, _)) => Err(GCPMetricsExporterInitError::InvalidConfig(format!(
+            "{} can not be used with a metric service",
+            option
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_validate_config() {
+        assert!(validate_config(&GCPMetricsExporterConfig::default()).is_ok());
+        for prefix in ["", "custom.googleapis.com/"] {
+            let config = GCPMetricsExporterConfig {
+                prefix: prefix.to_string(),
+                ..Default::default()
+            };
+            assert!(matches!(
+                validate_config(&config),
+                Err(GCPMetricsExporterInitError::InvalidConfig(_))
+            ));
+        }
+        let config = GCPMetricsExporterConfig {
+            project_id: Some("project-a".to_string()),
+            custom_monitored_resource_data: Some(MonitoredResourceDataConfig {
+                r#type: "global".to_string(),
+                labels: HashMap::from([("project_id".to_string(), "project-b".to_string())]),
+            }),
+            ..Default::default()
+        };
+        // only logged
+        assert!(validate_config(&config).is_ok());
+        #[cfg(feature = "spool")]
+        assert!(matches!(
+            validate_config(&GCPMetricsExporterConfig {
+                spool: Some(SpoolConfig::new("")),
+                ..Default::default()
+            }),
+            Err(GCPMetricsExporterInitError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_config_eq() {
+        let on_error = ExportErrorHandler::new(|_| {});
+        let config = GCPMetricsExporterConfig {
+            on_error: Some(on_error.clone()),
+            project_routing: ProjectRouting::Custom(Arc::new(|_, _| None)),
+            ..Default::default()
+        };
+        assert_eq!(config, config.clone());
+        assert_ne!(
+            config,
+            GCPMetricsExporterConfig {
+                on_error: Some(ExportErrorHandler::new(|_| {})),
+                ..config.clone()
+            }
+        );
+        assert_ne!(
+            config,
+            GCPMetricsExporterConfig {
+                prefix: "custom.googleapis.com".to_string(),
+                ..config.clone()
+            }
+        );
+    }
+}
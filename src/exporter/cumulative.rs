@@ -0,0 +1,347 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use google_cloud_api::model::metric_descriptor::MetricKind;
+use google_cloud_monitoring_v3::model::TimeSeries;
+use google_cloud_wkt::Timestamp;
+use opentelemetry_sdk::metrics::Temporality;
+
+use super::utils::MAX_POINT_AGE;
+
+/// Identifies a time series: metric type and labels, monitored resource type
+/// and labels.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SeriesKey {
+    metric_type: String,
+    labels: BTreeMap<String, String>,
+    resource_type: String,
+    resource_labels: BTreeMap<String, String>,
+}
+
+impl SeriesKey {
+    fn new(time_series: &TimeSeries) -> Self {
+        let (metric_type, labels) = match &time_series.metric {
+            Some(metric) => (metric.r#type.clone(), metric.labels.clone().into_iter().collect()),
+            None => (String::new(), BTreeMap::new()),
+        };
+        let (resource_type, resource_labels) = match &time_series.resource {
+            Some(resource) => (resource.r#type.clone(), resource.labels.clone().into_iter().collect()),
+            None => (String::new(), BTreeMap::new()),
+        };
+        Self {
+            metric_type,
+            labels,
+            resource_type,
+            resource_labels,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SeriesState {
+    /// Start time reported by the SDK.
+    reported_start: Timestamp,
+    /// Start time written to Cloud Monitoring, later than `reported_start`
+    /// when the reset overlapped the last written interval.
+    start: Timestamp,
+    end: Timestamp,
+    value: f64,
+    last_seen: SystemTime,
+}
+
+/// Outcome of [`CumulativeTracker::check_time_series`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CumulativeCheck {
+    /// Series that started a new interval.
+    pub(crate) resets: usize,
+    /// Series dropped because their end time is not after the last written one.
+    pub(crate) dropped: usize,
+}
+
+/// Last written interval and value of every cumulative time series.
+///
+/// Cloud Monitoring rejects the whole `CreateTimeSeries` call when a point of
+/// a cumulative series starts before the end of the last written point. When
+/// the SDK restarts an aggregation, or a counter goes down without a new start
+/// time, the series is reset: the new interval starts after the last written
+/// one instead of overlapping it. With the delta temporality every point
+/// starts when the previous collection ended, that is not a reset.
+#[derive(Debug, Default)]
+pub(crate) struct CumulativeTracker {
+    series: Mutex<HashMap<SeriesKey, SeriesState>>,
+}
+
+impl CumulativeTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the series not written for [`MAX_POINT_AGE`], they start over
+    /// anyway. Called once per export.
+    pub(crate) fn evict_stale(&self) {
+        self.evict_stale_at(SystemTime::now());
+    }
+
+    fn evict_stale_at(&self, now: SystemTime) {
+        self.lock()
+            .retain(|_, state| now.duration_since(state.last_seen).unwrap_or_default() < MAX_POINT_AGE);
+    }
+
+    /// Rewrites the start time of the cumulative series in `all_series` and
+    /// removes the points Cloud Monitoring would reject. The values of a
+    /// `monotonic` metric only go down when it is reset, an UpDownCounter
+    /// written as CUMULATIVE goes down anytime. The values of a `Delta`
+    /// metric are changes, they are not compared.
+    pub(crate) fn check_time_series(
+        &self,
+        all_series: &mut Vec<TimeSeries>,
+        monotonic: bool,
+        temporality: Temporality,
+    ) -> CumulativeCheck {
+        self.check_time_series_at(all_series, monotonic, temporality, SystemTime::now())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<SeriesKey, SeriesState>> {
+        self.series.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn check_time_series_at(
+        &self,
+        all_series: &mut Vec<TimeSeries>,
+        monotonic: bool,
+        temporality: Temporality,
+        now: SystemTime,
+    ) -> CumulativeCheck {
+        let mut check = CumulativeCheck::default();
+        let mut series = self.lock();
+        all_series.retain_mut(|time_series| {
+            if time_series.metric_kind != MetricKind::Cumulative {
+                return true;
+            }
+            let key = SeriesKey::new(time_series);
+            let Some(point) = time_series.points.first_mut() else {
+                return true;
+            };
+            let Some(value) = point.value.as_ref().and_then(point_value) else {
+                return true;
+            };
+            let Some(interval) = point.interval.as_mut() else {
+                return true;
+            };
+            let (Some(reported_start), Some(end)) = (interval.start_time, interval.end_time) else {
+                return true;
+            };
+            let previous = series.get(&key);
+            let mut start = reported_start;
+            if let Some(previous) = previous {
+                if end <= previous.end {
+                    // already written, or out of order
+                    check.dropped += 1;
+                    return false;
+                }
+                // a delta point starts when the previous collection ended, or
+                // later when the series was not collected in between, and
+                // keeps its start time
+                let next_window = temporality == Temporality::Delta && reported_start >= previous.end;
+                if !next_window {
+                    let reset = reported_start != previous.reported_start || (monotonic && value < previous.value);
+                    if reset {
+                        check.resets += 1;
+                        tracing::debug!(
+                            "GCPMetricsExporter: cumulative time series {} was reset",
+                            key.metric_type
+                        );
+                        if start <= previous.end {
+                            start = add_millis(previous.end, 1);
+                        }
+                    } else {
+                        start = previous.start;
+                    }
+                }
+            }
+            if start >= end {
+                // start and end time of a cumulative point must differ
+                let earlier = add_millis(end, -1);
+                match previous {
+                    Some(previous) if earlier <= previous.end => {
+                        check.dropped += 1;
+                        return false;
+                    }
+                    _ => start = earlier,
+                }
+            }
+            interval.start_time = Some(start);
+            series.insert(
+                key,
+                SeriesState {
+                    reported_start,
+                    start,
+                    end,
+                    value,
+                    last_seen: now,
+                },
+            );
+            true
+        });
+        check
+    }
+}
+
+/// Value compared to find counters that went down, the count of a distribution.
+fn point_value(value: &google_cloud_monitoring_v3::model::TypedValue) -> Option<f64> {
+    if let Some(value) = value.int64_value() {
+        return Some(*value as f64);
+    }
+    if let Some(value) = value.double_value() {
+        return Some(*value);
+    }
+    value.distribution_value().map(|distribution| distribution.count as f64)
+}
+
+fn add_millis(timestamp: Timestamp, millis: i32) -> Timestamp {
+    Timestamp::clamp(timestamp.seconds(), timestamp.nanos() + millis * 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use google_cloud_monitoring_v3::model::{Point, TimeInterval, TypedValue};
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    fn series(start: i64, end: i64, value: i64) -> TimeSeries {
+        TimeSeries::new()
+            .set_metric_kind(MetricKind::Cumulative)
+            .set_metric(google_cloud_api::model::Metric::new().set_type("workload.googleapis.com/requests"))
+            .set_points(vec![
+                Point::new()
+                    .set_interval(
+                        TimeInterval::new()
+                            .set_start_time(Timestamp::clamp(start, 0))
+                            .set_end_time(Timestamp::clamp(end, 0)),
+                    )
+                    .set_value(TypedValue::new().set_int64_value(value)),
+            ])
+    }
+
+    fn start_time(time_series: &TimeSeries) -> Timestamp {
+        time_series.points[0].interval.as_ref().unwrap().start_time.unwrap()
+    }
+
+    #[test]
+    fn test_cumulative_reset() {
+        let tracker = CumulativeTracker::new();
+        let now = SystemTime::now();
+
+        let mut all_series = vec![series(100, 160, 5)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, true, Temporality::Cumulative, now),
+            CumulativeCheck::default()
+        );
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(100, 0));
+
+        // restarted aggregation overlapping the last point
+        let mut all_series = vec![series(150, 220, 2)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, true, Temporality::Cumulative, now),
+            CumulativeCheck { resets: 1, dropped: 0 }
+        );
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(160, 1_000_000));
+
+        // the new interval is kept for the next points
+        let mut all_series = vec![series(150, 280, 4)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, true, Temporality::Cumulative, now),
+            CumulativeCheck::default()
+        );
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(160, 1_000_000));
+
+        // counter went down without a new start time
+        let mut all_series = vec![series(150, 340, 1)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, true, Temporality::Cumulative, now),
+            CumulativeCheck { resets: 1, dropped: 0 }
+        );
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(280, 1_000_000));
+
+        // restart after the last point keeps its start time
+        let mut all_series = vec![series(400, 460, 1)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, true, Temporality::Cumulative, now),
+            CumulativeCheck { resets: 1, dropped: 0 }
+        );
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(400, 0));
+
+        // out of order
+        let mut all_series = vec![series(400, 460, 3)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, true, Temporality::Cumulative, now),
+            CumulativeCheck { resets: 0, dropped: 1 }
+        );
+        assert!(all_series.is_empty());
+
+        // forgotten after 25 hours
+        tracker.evict_stale_at(now + MAX_POINT_AGE);
+        let mut all_series = vec![series(100, 120, 1)];
+        tracker.check_time_series_at(&mut all_series, true, Temporality::Cumulative, now + MAX_POINT_AGE);
+        assert_eq!(all_series.len(), 1);
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(100, 0));
+    }
+
+    #[test]
+    fn test_cumulative_up_down_counter() {
+        let tracker = CumulativeTracker::new();
+        let now = SystemTime::now();
+        let mut all_series = vec![series(100, 160, 5)];
+        tracker.check_time_series_at(&mut all_series, false, Temporality::Cumulative, now);
+        // going down is not a reset
+        let mut all_series = vec![series(100, 220, -2)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, false, Temporality::Cumulative, now),
+            CumulativeCheck::default()
+        );
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(100, 0));
+    }
+
+    #[test]
+    fn test_cumulative_delta() {
+        let tracker = CumulativeTracker::new();
+        let now = SystemTime::now();
+        let mut all_series = vec![series(100, 160, 5)];
+        tracker.check_time_series_at(&mut all_series, true, Temporality::Delta, now);
+
+        // next window, a smaller change is not a reset
+        let mut all_series = vec![series(160, 220, 2)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, true, Temporality::Delta, now),
+            CumulativeCheck::default()
+        );
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(160, 0));
+
+        // not collected in between
+        let mut all_series = vec![series(280, 340, 1)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, true, Temporality::Delta, now),
+            CumulativeCheck::default()
+        );
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(280, 0));
+
+        // overlapping the last point
+        let mut all_series = vec![series(300, 400, 1)];
+        assert_eq!(
+            tracker.check_time_series_at(&mut all_series, true, Temporality::Delta, now),
+            CumulativeCheck { resets: 1, dropped: 0 }
+        );
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(340, 1_000_000));
+    }
+
+    #[test]
+    fn test_cumulative_same_start_and_end() {
+        let tracker = CumulativeTracker::new();
+        let mut all_series = vec![series(100, 100, 1)];
+        tracker.check_time_series_at(&mut all_series, true, Temporality::Cumulative, SystemTime::now());
+        assert_eq!(start_time(&all_series[0]), Timestamp::clamp(99, 999_000_000));
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::{BTreeMap, HashSet};
+
+use google_cloud_api::model::MetricDescriptor;
+use google_cloud_monitoring_v3::{
+    client::MetricService,
+    model::{CreateMetricDescriptorRequest, DeleteMetricDescriptorRequest, ListMetricDescriptorsRequest},
+};
+
+use super::{
+    GCPMetricsExporterConfig, GCPMetricsExporterInitError,
+    builder::{connect, validate_config},
+};
+
+/// Lists, creates and deletes the metric descriptors of a project, used by
+/// the `gcp-metric-descriptors` binary of the `cli` feature.
+#[derive(Debug, Clone)]
+pub struct MetricDescriptorAdmin {
+    client: MetricService,
+    project_id: String,
+}
+
+/// Difference between the metric descriptors of a project and the wanted
+/// ones, by metric type, see [`diff_metric_descriptors`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct MetricDescriptorDiff {
+    /// Wanted but not in the project.
+    pub missing: Vec<String>,
+    /// In the project but not wanted.
+    pub obsolete: Vec<String>,
+    /// Different kind, value type, unit or label keys. Cloud Monitoring
+    /// does not update descriptors, they have to be deleted and recreated.
+    pub changed: Vec<String>,
+}
+
+impl MetricDescriptorAdmin {
+    pub fn new(client: MetricService, project_id: impl Into<String>) -> Self {
+        Self {
+            client,
+            project_id: project_id.into(),
+        }
+    }
+
+    /// Builds the client like [`crate::GCPMetricsExporter::init`] does, with
+    /// the endpoint, credentials, project id and dry-run options of `config`.
+    /// In dry-run mode the project has no descriptors and the create and
+    /// delete requests are written to the dry-run output.
+    pub async fn from_config(config: &GCPMetricsExporterConfig) -> Result<Self, GCPMetricsExporterInitError> {
+        validate_config(config)?;
+        let (client, project_id, project_id_source) = connect(None, config).await?;
+        tracing::debug!(
+            "MetricDescriptorAdmin: using project id {} from {}",
+            project_id,
+            project_id_source
+        );
+        Ok(Self::new(client, project_id))
+    }
+
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
+    /// Descriptors whose type starts with `prefix`, e.g. `workload.googleapis.com/`,
+    /// sorted by type.
+    pub async fn list(&self, prefix: &str) -> Result<Vec<MetricDescriptor>, google_cloud_gax::error::Error> {
+        let mut descriptors = Vec::new();
+        let mut page_token = String::new();
+        loop {
+            let resp = self
+                .client
+                .list_metric_descriptors()
+                .with_request(
+                    ListMetricDescriptorsRequest::new()
+                        .set_name(format!("projects/{}", self.project_id))
+                        .set_filter(format!("metric.type = starts_with({})", filter_string(prefix)))
+                        .set_page_token(page_token),
+                )
+                .send()
+                .await?;
+            descriptors.extend(resp.metric_descriptors);
+            if resp.next_page_token.is_empty() {
+                break;
+            }
+            page_token = resp.next_page_token;
+        }
+        descriptors.sort_by(|a, b| a.r#type.cmp(&b.r#type));
+        Ok(descriptors)
+    }
+
+    /// Creates each descriptor, stops at the first error.
+    pub async fn create(&self, descriptors: &[MetricDescriptor]) -> Result<(), google_cloud_gax::error::Error> {
+        for descriptor in descriptors {
+            self.client
+                .create_metric_descriptor()
+                .with_request(
+                    CreateMetricDescriptorRequest::new()
+                        .set_name(format!("projects/{}", self.project_id))
+                        .set_metric_descriptor(descriptor.clone()),
+                )
+                .send()
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the descriptor of each metric type, stops at the first error.
+    /// The time series of a deleted descriptor are deleted too.
+    pub async fn delete(&self, metric_types: &[String]) -> Result<(), google_cloud_gax::error::Error> {
+        for metric_type in metric_types {
+            self.client
+                .delete_metric_descriptor()
+                .with_request(DeleteMetricDescriptorRequest::new().set_name(format!(
+                    "projects/{}/metricDescriptors/{}",
+                    self.project_id, metric_type
+                )))
+                .send()
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// A `prefix` to prune descriptors under must be specific to an app, e.g.
+/// `workload.googleapis.com/checkout.`: the domain alone, e.g.
+/// `workload.googleapis.com/`, is shared by every workload of the project.
+pub fn validate_prune_prefix(prefix: &str) -> Result<(), String> {
+    match prefix.split_once('/') {
+        Some((domain, path)) if !domain.is_empty() && !path.is_empty() => Ok(()),
+        _ => Err(format!(
+            "prune prefix must be specific to the app, e.g. workload.googleapis.com/my_app., got {:?}",
+            prefix
+        )),
+    }
+}
+
+/// `value` as a double quoted string of the Monitoring filter syntax.
+fn filter_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Compares the descriptors of a project with the `wanted` ones.
+pub fn diff_metric_descriptors(existing: &[MetricDescriptor], wanted: &[MetricDescriptor]) -> MetricDescriptorDiff {
+    let existing = existing
+        .iter()
+        .map(|descriptor| (descriptor.r#type.as_str(), descriptor))
+        .collect::<BTreeMap<_, _>>();
+    let wanted = wanted
+        .iter()
+        .map(|descriptor| (descriptor.r#type.as_str(), descriptor))
+        .collect::<BTreeMap<_, _>>();
+    let mut diff = MetricDescriptorDiff::default();
+    for (metric_type, wanted) in &wanted {
+        match existing.get(metric_type) {
+            None => diff.missing.push(metric_type.to_string()),
+            Some(existing) if !same_shape(existing, wanted) => diff.changed.push(metric_type.to_string()),
+            Some(_) => {}
+        }
+    }
+    diff.obsolete = existing
+        .keys()
+        .filter(|metric_type| !wanted.contains_key(*metric_type))
+        .map(|metric_type| metric_type.to_string())
+        .collect();
+    diff
+}
+
+fn same_shape(a: &MetricDescriptor, b: &MetricDescriptor) -> bool {
+    let label_keys = |descriptor: &MetricDescriptor| {
+        descriptor
+            .labels
+            .iter()
+            .map(|label| label.key.clone())
+            .collect::<HashSet<_>>()
+    };
+    a.metric_kind == b.metric_kind && a.value_type == b.value_type && a.unit == b.unit && label_keys(a) == label_keys(b)
+}
+
+/// Reads metric descriptors from `json`, one or more JSON documents, each one
+/// of:
+/// - a descriptor or an array of descriptors, e.g. the output of `list`
+/// - a dry-run record, only `CreateMetricDescriptor` records are kept, so the
+///   dry-run output of an app lists the descriptors of its instruments
+///
+/// The last descriptor of a type wins.
+pub fn read_metric_descriptors(json: &[u8]) -> Result<Vec<MetricDescriptor>, serde_json::Error> {
+    let mut descriptors = BTreeMap::new();
+    for document in serde_json::Deserializer::from_slice(json).into_iter::<serde_json::Value>() {
+        let values = match document? {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            let descriptor = match value.get("method").and_then(|method| method.as_str()) {
+                Some("CreateMetricDescriptor") => {
+                    serde_json::from_value::<CreateMetricDescriptorRequest>(value["request"].clone())?
+                        .metric_descriptor
+                        .unwrap_or_default()
+                }
+                Some(_) => continue,
+                None => serde_json::from_value::<MetricDescriptor>(value)?,
+            };
+            descriptors.insert(descriptor.r#type.clone(), descriptor);
+        }
+    }
+    Ok(descriptors.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    #[test]
+    fn test_filter_string() {
+        assert_eq!(
+            filter_string("workload.googleapis.com/"),
+            "\"workload.googleapis.com/\""
+        );
+        assert_eq!(filter_string(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+
+    #[test]
+    fn test_validate_prune_prefix() {
+        for prefix in ["workload.googleapis.com/checkout.", "custom.googleapis.com/checkout/"] {
+            assert!(validate_prune_prefix(prefix).is_ok(), "{}", prefix);
+        }
+        for prefix in ["", "workload.googleapis.com", "workload.googleapis.com/", "/checkout"] {
+            assert!(validate_prune_prefix(prefix).is_err(), "{}", prefix);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+pub(crate) const DRY_RUN_PROJECT_ID: &str = "dry-run";
+
+/// Where a dry-run exporter writes the requests, see [`DryRunConfig`].
+#[derive(Clone, Default)]
+pub enum DryRunOutput {
+    #[default]
+    Stdout,
+    /// Appends to the file, it is created if missing.
+    File(PathBuf),
+    Writer(Arc<Mutex<dyn Write + Send>>),
+}
+
+impl DryRunOutput {
+    pub fn writer(writer: impl Write + Send + 'static) -> Self {
+        Self::Writer(Arc::new(Mutex::new(writer)))
+    }
+}
+
+impl fmt::Debug for DryRunOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stdout => f.write_str("Stdout"),
+            Self::File(path) => f.debug_tuple("File").field(path).finish(),
+            Self::Writer(_) => f.write_str("Writer"),
+        }
+    }
+}
+
+/// Writers are equal when they are clones of the same writer.
+impl PartialEq for DryRunOutput {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Stdout, Self::Stdout) => true,
+            (Self::File(path), Self::File(other_path)) => path == other_path,
+            (Self::Writer(writer), Self::Writer(other_writer)) => Arc::ptr_eq(writer, other_writer),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for DryRunOutput {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DryRunFormat {
+    /// One pretty printed JSON document per request.
+    #[default]
+    Json,
+    /// One JSON document per line.
+    Ndjson,
+}
+
+/// Writes every `CreateMetricDescriptor` and `CreateTimeSeries` request as
+/// `{"method": "CreateTimeSeries", "request": {...}}` instead of sending it.
+/// `DeleteMetricDescriptor` requests are written the same way and the
+/// project has no metric descriptors.
+///
+/// Credentials are not loaded and the project id is not detected, it is
+/// `dry-run` unless configured.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DryRunConfig {
+    pub output: DryRunOutput,
+    pub format: DryRunFormat,
+}
+
+/// `MetricService` stub writing the requests to the dry-run output.
+#[derive(Clone)]
+pub(crate) struct DryRunMetricService {
+    writer: Arc<Mutex<dyn Write + Send>>,
+    format: DryRunFormat,
+}
+
+impl fmt::Debug for DryRunMetricService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DryRunMetricService")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl DryRunMetricService {
+    pub(crate) fn new(config: DryRunConfig) -> std::io::Result<Self> {
+        let writer: Arc<Mutex<dyn Write + Send>> = match config.output {
+            DryRunOutput::Stdout => Arc::new(Mutex::new(std::io::stdout())),
+            DryRunOutput::File(path) => Arc::new(Mutex::new(OpenOptions::new().create(true).append(true).open(path)?)),
+            DryRunOutput::Writer(writer) => writer,
+        };
+        Ok(Self {
+            writer,
+            format: config.format,
+        })
+    }
+
+    fn write<T: serde::Serialize>(&self, method: &str, req: &T) -> google_cloud_gax::Result<()> {
+        let record = serde_json::json!({ "method": method, "request": req });
+        let mut line = match self.format {
+            DryRunFormat::Json => serde_json::to_vec_pretty(&record),
+            DryRunFormat::Ndjson => serde_json::to_vec(&record),
+        }
+        .map_err(google_cloud_gax::error::Error::ser)?;
+        line.push(b'\n');
+        let mut writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        writer
+            .write_all(&line)
+            .and_then(|_| writer.flush())
+            .map_err(google_cloud_gax::error::Error::io)
+    }
+}
+
+impl google_cloud_monitoring_v3::stub::MetricService for DryRunMetricService {
+    async fn create_metric_descriptor(
+        &self,
+        req: google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> google_cloud_monitoring_v3::Result<
+        google_cloud_gax::response::Response<google_cloud_api::model::MetricDescriptor>,
+    > {
+        self.write("CreateMetricDescriptor", &req)?;
+        Ok(google_cloud_gax::response::Response::from(
+            req.metric_descriptor.unwrap_or_default(),
+        ))
+    }
+
+    async fn list_metric_descriptors(
+        &self,
+        _req: google_cloud_monitoring_v3::model::ListMetricDescriptorsRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> google_cloud_monitoring_v3::Result<
+        google_cloud_gax::response::Response<google_cloud_monitoring_v3::model::ListMetricDescriptorsResponse>,
+    > {
+        Ok(google_cloud_gax::response::Response::from(
+            google_cloud_monitoring_v3::model::ListMetricDescriptorsResponse::new(),
+        ))
+    }
+
+    async fn delete_metric_descriptor(
+        &self,
+        req: google_cloud_monitoring_v3::model::DeleteMetricDescriptorRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> google_cloud_monitoring_v3::Result<google_cloud_gax::response::Response<()>> {
+        self.write("DeleteMetricDescriptor", &req)?;
+        Ok(google_cloud_gax::response::Response::from(()))
+    }
+
+    async fn create_time_series(
+        &self,
+        req: google_cloud_monitoring_v3::model::CreateTimeSeriesRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> google_cloud_monitoring_v3::Result<google_cloud_gax::response::Response<()>> {
+        self.write("CreateTimeSeries", &req)?;
+        Ok(google_cloud_gax::response::Response::from(()))
+    }
+}
@@ -0,0 +1,154 @@
+use google_cloud_gax::error::rpc::Code;
+use opentelemetry_sdk::error::OTelSdkError;
+use std::{fmt, sync::Arc};
+
+/// The step of an export that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportOperation {
+    /// `CreateMetricDescriptor` RPC.
+    CreateMetricDescriptor,
+    /// `CreateTimeSeries` RPC.
+    CreateTimeSeries,
+    /// Reading or writing the on-disk spool (`spool` feature).
+    Spool,
+}
+
+impl fmt::Display for ExportOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExportOperation::CreateMetricDescriptor => "CreateMetricDescriptor",
+            ExportOperation::CreateTimeSeries => "CreateTimeSeries",
+            ExportOperation::Spool => "spool",
+        })
+    }
+}
+
+/// What failed, and which metrics were affected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportErrorDetails {
+    pub operation: ExportOperation,
+    /// gRPC status code returned by Cloud Monitoring, `None` for transport
+    /// and local errors.
+    pub code: Option<Code>,
+    pub message: String,
+    /// Metric types (`{prefix}/{name}`) of the failed request.
+    pub metric_types: Vec<String>,
+    /// Number of time series in the failed request, 0 for metric descriptors.
+    pub time_series_count: usize,
+}
+
+/// Failure of an export to Google Cloud Monitoring.
+///
+/// Passed to [`crate::GCPMetricsExporterConfig::on_error`] for every failure
+/// and converted to [`OTelSdkError`] for the OpenTelemetry SDK.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum GCPMetricsExportError {
+    #[error(
+        "{} permission denied, need role `roles/monitoring.metricWriter` or permissions `monitoring.metricDescriptors.create`, `monitoring.timeSeries.create`: {}",
+        .0.operation,
+        .0.message
+    )]
+    PermissionDenied(ExportErrorDetails),
+    #[error("{} quota exceeded: {}", .0.operation, .0.message)]
+    QuotaExceeded(ExportErrorDetails),
+    #[error("{} invalid argument: {}", .0.operation, .0.message)]
+    InvalidArgument(ExportErrorDetails),
+    #[error("{} conflict: {}", .0.operation, .0.message)]
+    Conflict(ExportErrorDetails),
+    #[error("{} unavailable: {}", .0.operation, .0.message)]
+    Unavailable(ExportErrorDetails),
+    #[error("{} failed: {}", .0.operation, .0.message)]
+    Other(ExportErrorDetails),
+}
+
+impl GCPMetricsExportError {
+    pub(crate) fn from_rpc(
+        operation: ExportOperation,
+        err: &google_cloud_gax::error::Error,
+        metric_types: Vec<String>,
+        time_series_count: usize,
+    ) -> Self {
+        let code = err.status().map(|status| status.code);
+        let details = ExportErrorDetails {
+            operation,
+            code,
+            message: err.to_string(),
+            metric_types,
+            time_series_count,
+        };
+        match code {
+            Some(Code::PermissionDenied) | Some(Code::Unauthenticated) => Self::PermissionDenied(details),
+            Some(Code::ResourceExhausted) => Self::QuotaExceeded(details),
+            Some(Code::InvalidArgument) | Some(Code::OutOfRange) => Self::InvalidArgument(details),
+            Some(Code::AlreadyExists) | Some(Code::Aborted) | Some(Code::FailedPrecondition) => Self::Conflict(details),
+            Some(Code::Unavailable) | Some(Code::DeadlineExceeded) => Self::Unavailable(details),
+            Some(_) => Self::Other(details),
+            None if err.is_timeout() || err.is_io() || err.is_connect() || err.is_transport() => {
+                Self::Unavailable(details)
+            }
+            None => Self::Other(details),
+        }
+    }
+
+    #[cfg_attr(not(feature = "spool"), allow(dead_code))]
+    pub(crate) fn spool(err: &std::io::Error) -> Self {
+        Self::Other(ExportErrorDetails {
+            operation: ExportOperation::Spool,
+            code: None,
+            message: err.to_string(),
+            metric_types: Vec::new(),
+            time_series_count: 0,
+        })
+    }
+
+    pub fn details(&self) -> &ExportErrorDetails {
+        match self {
+            Self::PermissionDenied(details)
+            | Self::QuotaExceeded(details)
+            | Self::InvalidArgument(details)
+            | Self::Conflict(details)
+            | Self::Unavailable(details)
+            | Self::Other(details) => details,
+        }
+    }
+
+    pub fn code(&self) -> Option<Code> {
+        self.details().code
+    }
+}
+
+impl From<GCPMetricsExportError> for OTelSdkError {
+    fn from(err: GCPMetricsExportError) -> Self {
+        OTelSdkError::InternalFailure(format!("GCPMetricsExporter: {}", err))
+    }
+}
+
+/// Callback invoked with every [`GCPMetricsExportError`], see
+/// [`crate::GCPMetricsExporterConfig::on_error`].
+#[derive(Clone)]
+pub struct ExportErrorHandler(Arc<dyn Fn(&GCPMetricsExportError) + Send + Sync>);
+
+impl ExportErrorHandler {
+    pub fn new(handler: impl Fn(&GCPMetricsExportError) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(handler))
+    }
+
+    pub(crate) fn call(&self, err: &GCPMetricsExportError) {
+        (self.0)(err)
+    }
+}
+
+impl fmt::Debug for ExportErrorHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ExportErrorHandler")
+    }
+}
+
+/// Handlers are equal when they are clones of the same handler.
+impl PartialEq for ExportErrorHandler {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ExportErrorHandler {}
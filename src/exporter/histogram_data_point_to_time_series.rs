@@ -1,4 +1,4 @@
-use super::{UNIQUE_IDENTIFIER_KEY, utils::kv_map_normalize_k_v};
+use super::{interval, utils::kv_map_normalize_k_v};
 use crate::exporter::to_f64::ToF64;
 use opentelemetry_sdk::metrics::data;
 use std::time::SystemTime;
@@ -8,32 +8,10 @@ pub fn convert<T: ToF64 + Copy>(
     start_time: &SystemTime,
     time: &SystemTime,
     descriptor: &google_cloud_api::model::MetricDescriptor,
-    monitored_resource_data: &Option<google_cloud_api::model::MonitoredResource>,
-    add_unique_identifier: bool,
-    unique_identifier: &str,
+    unique_identifier: Option<(&str, &str)>,
 ) -> google_cloud_monitoring_v3::model::TimeSeries {
     let mut point = google_cloud_monitoring_v3::model::Point::new();
-    let mut interval = google_cloud_monitoring_v3::model::TimeInterval::new();
-
-    let data_point_start_time = start_time.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos();
-    interval.start_time = Some(
-        google_cloud_wkt::Timestamp::new(
-            (data_point_start_time / 1_000_000_000) as i64,
-            (data_point_start_time % 1_000_000_000) as i32,
-        )
-        .unwrap(),
-    );
-
-    let data_point_time = time.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos();
-    interval.end_time = Some(
-        google_cloud_wkt::Timestamp::new(
-            (data_point_time / 1_000_000_000) as i64,
-            (data_point_time % 1_000_000_000) as i32,
-        )
-        .unwrap(),
-    );
-
-    point.interval = Some(interval);
+    point.interval = Some(interval::time_interval(&descriptor.metric_kind, Some(start_time), time));
 
     let distribution = google_cloud_api::model::Distribution::new()
         .set_count(data_point.count() as i64)
@@ -56,11 +34,11 @@ pub fn convert<T: ToF64 + Copy>(
         .attributes()
         .map(kv_map_normalize_k_v)
         .collect::<std::collections::HashMap<String, String>>();
-    if add_unique_identifier {
-        labels.insert(UNIQUE_IDENTIFIER_KEY.to_string(), unique_identifier.to_string());
+    if let Some((key, value)) = unique_identifier {
+        labels.insert(key.to_string(), value.to_string());
     }
 
-    let mut time_series = google_cloud_monitoring_v3::model::TimeSeries::new()
+    google_cloud_monitoring_v3::model::TimeSeries::new()
         .set_metric_kind(descriptor.metric_kind.clone())
         .set_value_type(descriptor.value_type.clone())
         .set_metric(
@@ -70,13 +48,7 @@ pub fn convert<T: ToF64 + Copy>(
         )
         .set_points(vec![point])
         .set_unit(descriptor.unit.clone())
-        .set_description("".to_string());
-
-    if let Some(resource) = monitored_resource_data {
-        time_series = time_series.set_resource(resource.clone());
-    }
-
-    time_series
+        .set_description("".to_string())
 }
 
 pub fn convert_exponential<T: ToF64 + Copy>(
@@ -84,32 +56,10 @@ pub fn convert_exponential<T: ToF64 + Copy>(
     start_time: &SystemTime,
     time: &SystemTime,
     descriptor: &google_cloud_api::model::MetricDescriptor,
-    monitored_resource_data: &Option<google_cloud_api::model::MonitoredResource>,
-    add_unique_identifier: bool,
-    unique_identifier: &str,
+    unique_identifier: Option<(&str, &str)>,
 ) -> google_cloud_monitoring_v3::model::TimeSeries {
     let mut point = google_cloud_monitoring_v3::model::Point::new();
-    let mut interval = google_cloud_monitoring_v3::model::TimeInterval::new();
-
-    let data_point_start_time = start_time.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos();
-    interval.start_time = Some(
-        google_cloud_wkt::Timestamp::new(
-            (data_point_start_time / 1_000_000_000) as i64,
-            (data_point_start_time % 1_000_000_000) as i32,
-        )
-        .unwrap(),
-    );
-
-    let data_point_time = time.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos();
-    interval.end_time = Some(
-        google_cloud_wkt::Timestamp::new(
-            (data_point_time / 1_000_000_000) as i64,
-            (data_point_time % 1_000_000_000) as i32,
-        )
-        .unwrap(),
-    );
-
-    point.interval = Some(interval);
+    point.interval = Some(interval::time_interval(&descriptor.metric_kind, Some(start_time), time));
 
     // Adapted from https://github.com/GoogleCloudPlatform/opentelemetry-operations-go/blob/v1.8.0/exporter/collector/metrics.go#L582
 
@@ -163,11 +113,11 @@ pub fn convert_exponential<T: ToF64 + Copy>(
         .attributes()
         .map(|kv| (kv_map_normalize_k_v(kv).0, kv_map_normalize_k_v(kv).1))
         .collect::<std::collections::HashMap<String, String>>();
-    if add_unique_identifier {
-        labels.insert(UNIQUE_IDENTIFIER_KEY.to_string(), unique_identifier.to_string());
+    if let Some((key, value)) = unique_identifier {
+        labels.insert(key.to_string(), value.to_string());
     }
 
-    let mut time_series = google_cloud_monitoring_v3::model::TimeSeries::new()
+    google_cloud_monitoring_v3::model::TimeSeries::new()
         .set_metric_kind(descriptor.metric_kind.clone())
         .set_value_type(descriptor.value_type.clone())
         .set_metric(
@@ -176,11 +126,5 @@ pub fn convert_exponential<T: ToF64 + Copy>(
                 .set_labels(labels),
         )
         .set_points(vec![point])
-        .set_unit(descriptor.unit.clone());
-
-    if let Some(resource) = monitored_resource_data {
-        time_series = time_series.set_resource(resource.clone());
-    }
-
-    time_series
+        .set_unit(descriptor.unit.clone())
 }
@@ -0,0 +1,139 @@
+//! Metric kind and point interval of every instrument.
+//!
+//! | Instrument                                 | SDK data            | Metric kind  | Point interval      |
+//! |--------------------------------------------|---------------------|--------------|---------------------|
+//! | Counter, ObservableCounter                 | monotonic sum       | `CUMULATIVE` | start .. collection |
+//! | same with `counters_as_gauge`              |                     | `GAUGE`      | collection          |
+//! | UpDownCounter, ObservableUpDownCounter     | non-monotonic sum   | `GAUGE`      | collection          |
+//! | same with `up_down_counters_as_cumulative` |                     | `CUMULATIVE` | start .. collection |
+//! | Gauge, ObservableGauge                     | gauge               | `GAUGE`      | collection          |
+//! | Histogram                                  | (exp.) histogram    | `CUMULATIVE` | start .. collection |
+//!
+//! The start time of a `CUMULATIVE` point is the start of the aggregation:
+//! it stays fixed with the cumulative temporality, and is the start of the
+//! collection window with the delta temporality, each point being a new
+//! interval then. `GAUGE` points only have an end time, Cloud Monitoring
+//! rejects a gauge point with a different start time. A kind declared in the
+//! [`super::MetricSchema`] follows the same rule.
+
+use google_cloud_api::model::metric_descriptor::MetricKind;
+use google_cloud_monitoring_v3::model::TimeInterval;
+use opentelemetry_sdk::metrics::{
+    Temporality,
+    data::{AggregatedMetrics, MetricData},
+};
+use std::time::SystemTime;
+
+/// Metric kind of a sum, see the table above.
+pub(crate) fn sum_metric_kind(
+    is_monotonic: bool,
+    counters_as_gauge: bool,
+    up_down_counters_as_cumulative: bool,
+) -> MetricKind {
+    match (is_monotonic, counters_as_gauge, up_down_counters_as_cumulative) {
+        (true, false, _) | (false, _, true) => MetricKind::Cumulative,
+        (true, true, _) | (false, _, false) => MetricKind::Gauge,
+    }
+}
+
+/// Whether a value going down means the aggregation was reset: true for
+/// counters and histograms, false for UpDownCounters and gauges.
+pub(crate) fn is_monotonic(data: &AggregatedMetrics) -> bool {
+    fn monotonic<T>(data: &MetricData<T>) -> bool {
+        match data {
+            MetricData::Sum(m) => m.is_monotonic(),
+            MetricData::Gauge(_) => false,
+            MetricData::Histogram(_) | MetricData::ExponentialHistogram(_) => true,
+        }
+    }
+    match data {
+        AggregatedMetrics::F64(v) => monotonic(v),
+        AggregatedMetrics::I64(v) => monotonic(v),
+        AggregatedMetrics::U64(v) => monotonic(v),
+    }
+}
+
+/// Temporality of the aggregation, gauges are cumulative: their points do
+/// not start when the previous one ended.
+pub(crate) fn temporality(data: &AggregatedMetrics) -> Temporality {
+    fn temporality<T>(data: &MetricData<T>) -> Temporality {
+        match data {
+            MetricData::Sum(m) => m.temporality(),
+            MetricData::Histogram(m) => m.temporality(),
+            MetricData::ExponentialHistogram(m) => m.temporality(),
+            MetricData::Gauge(_) => Temporality::Cumulative,
+        }
+    }
+    match data {
+        AggregatedMetrics::F64(v) => temporality(v),
+        AggregatedMetrics::I64(v) => temporality(v),
+        AggregatedMetrics::U64(v) => temporality(v),
+    }
+}
+
+/// Interval of a point of kind `metric_kind`, `start_time` is only set for
+/// `CUMULATIVE` and `DELTA` points.
+pub(crate) fn time_interval(
+    metric_kind: &MetricKind,
+    start_time: Option<&SystemTime>,
+    time: &SystemTime,
+) -> TimeInterval {
+    let mut interval = TimeInterval::new().set_end_time(timestamp(time));
+    if let (Some(start_time), true) = (
+        start_time,
+        matches!(metric_kind, MetricKind::Cumulative | MetricKind::Delta),
+    ) {
+        interval.start_time = Some(timestamp(start_time));
+    }
+    interval
+}
+
+fn timestamp(time: &SystemTime) -> google_cloud_wkt::Timestamp {
+    let nanos = time.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos();
+    google_cloud_wkt::Timestamp::new((nanos / 1_000_000_000) as i64, (nanos % 1_000_000_000) as i32).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sum_metric_kind() {
+        // (monotonic, counters_as_gauge, up_down_counters_as_cumulative)
+        let cases = [
+            ((true, false, false), MetricKind::Cumulative),
+            ((true, false, true), MetricKind::Cumulative),
+            ((true, true, false), MetricKind::Gauge),
+            ((true, true, true), MetricKind::Gauge),
+            ((false, false, false), MetricKind::Gauge),
+            ((false, true, false), MetricKind::Gauge),
+            ((false, false, true), MetricKind::Cumulative),
+            ((false, true, true), MetricKind::Cumulative),
+        ];
+        for ((monotonic, counters_as_gauge, up_down_counters_as_cumulative), kind) in cases {
+            assert_eq!(
+                sum_metric_kind(monotonic, counters_as_gauge, up_down_counters_as_cumulative),
+                kind,
+                "{:?}",
+                (monotonic, counters_as_gauge, up_down_counters_as_cumulative)
+            );
+        }
+    }
+
+    #[test]
+    fn test_time_interval() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_millis(1_500);
+        let end = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        let with_start = TimeInterval::new()
+            .set_start_time(google_cloud_wkt::Timestamp::new(1, 500_000_000).unwrap())
+            .set_end_time(google_cloud_wkt::Timestamp::new(60, 0).unwrap());
+        let end_only = TimeInterval::new().set_end_time(google_cloud_wkt::Timestamp::new(60, 0).unwrap());
+
+        assert_eq!(time_interval(&MetricKind::Cumulative, Some(&start), &end), with_start);
+        assert_eq!(time_interval(&MetricKind::Delta, Some(&start), &end), with_start);
+        assert_eq!(time_interval(&MetricKind::Gauge, Some(&start), &end), end_only);
+        assert_eq!(time_interval(&MetricKind::Cumulative, None, &end), end_only);
+    }
+}
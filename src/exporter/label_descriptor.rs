@@ -0,0 +1,134 @@
+use google_cloud_api::model::{LabelDescriptor, label_descriptor::ValueType};
+
+use super::utils::normalize_label_key;
+
+const UNIQUE_IDENTIFIER_DESCRIPTION: &str = "Identifies the exporter that wrote the time series.";
+
+/// Description and value type of a metric label in the metric descriptors,
+/// shown in Metrics Explorer.
+///
+/// `key` is the attribute key, e.g. `http.route`, or the label key it is
+/// written as, e.g. `http_route`. Applies to every metric unless
+/// `metric_names` is set, an entry naming the metric wins over one that
+/// does not.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LabelDescriptorConfig {
+    pub key: String,
+    pub description: String,
+    /// `STRING` by default.
+    pub value_type: ValueType,
+    pub metric_names: Vec<String>,
+}
+
+/// Label descriptor of the attribute `key` of the metric `metric_name`.
+pub(crate) fn label_descriptor(configs: &[LabelDescriptorConfig], metric_name: &str, key: &str) -> LabelDescriptor {
+    let label_key = normalize_label_key(key);
+    let matching = |config: &&LabelDescriptorConfig| config.key == key || config.key == label_key;
+    let config = configs
+        .iter()
+        .filter(matching)
+        .find(|config| config.metric_names.iter().any(|name| name == metric_name))
+        .or_else(|| {
+            configs
+                .iter()
+                .filter(matching)
+                .find(|config| config.metric_names.is_empty())
+        });
+    let descriptor = LabelDescriptor::new().set_key(label_key);
+    match config {
+        Some(config) => descriptor
+            .set_description(config.description.clone())
+            .set_value_type(config.value_type.clone()),
+        None => descriptor,
+    }
+}
+
+/// Label descriptor of the unique identifier label `key`, described by default.
+pub(crate) fn unique_identifier_label_descriptor(
+    configs: &[LabelDescriptorConfig],
+    metric_name: &str,
+    key: &str,
+) -> LabelDescriptor {
+    let mut descriptor = label_descriptor(configs, metric_name, key);
+    if descriptor.description.is_empty() {
+        descriptor.description = UNIQUE_IDENTIFIER_DESCRIPTION.to_string();
+    }
+    descriptor
+}
+
+/// Keys are not empty and value types are known.
+pub(crate) fn validate_label_descriptors(configs: &[LabelDescriptorConfig]) -> Result<(), String> {
+    for config in configs {
+        if config.key.is_empty() {
+            return Err("label_descriptors keys must not be empty".to_string());
+        }
+        if !matches!(
+            config.value_type,
+            ValueType::String | ValueType::Bool | ValueType::Int64
+        ) {
+            return Err(format!(
+                "label descriptor {:?} has an invalid value_type {:?}",
+                config.key, config.value_type
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    #[test]
+    fn test_label_descriptor() {
+        let configs = vec![
+            LabelDescriptorConfig {
+                key: "http.route".to_string(),
+                description: "Route template.".to_string(),
+                ..Default::default()
+            },
+            LabelDescriptorConfig {
+                key: "http_route".to_string(),
+                description: "Route of the upstream call.".to_string(),
+                metric_names: vec!["client.requests".to_string()],
+                ..Default::default()
+            },
+            LabelDescriptorConfig {
+                key: "status".to_string(),
+                value_type: ValueType::Int64,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            label_descriptor(&configs, "server.requests", "http.route"),
+            LabelDescriptor::new()
+                .set_key("http_route")
+                .set_description("Route template.")
+        );
+        assert_eq!(
+            label_descriptor(&configs, "client.requests", "http.route"),
+            LabelDescriptor::new()
+                .set_key("http_route")
+                .set_description("Route of the upstream call.")
+        );
+        assert_eq!(
+            label_descriptor(&configs, "server.requests", "status"),
+            LabelDescriptor::new()
+                .set_key("status")
+                .set_value_type(ValueType::Int64)
+        );
+        assert_eq!(
+            label_descriptor(&configs, "server.requests", "user"),
+            LabelDescriptor::new().set_key("user")
+        );
+        assert_eq!(
+            unique_identifier_label_descriptor(&[], "server.requests", "opentelemetry_id"),
+            LabelDescriptor::new()
+                .set_key("opentelemetry_id")
+                .set_description(UNIQUE_IDENTIFIER_DESCRIPTION)
+        );
+        assert!(validate_label_descriptors(&configs).is_ok());
+        assert!(validate_label_descriptors(&[LabelDescriptorConfig::default()]).is_err());
+    }
+}
@@ -1,10 +1,29 @@
+mod builder;
+mod client;
+mod cumulative;
 mod data_point_to_time_series;
+#[cfg(feature = "cli")]
+mod descriptor_admin;
+mod dry_run;
+mod error;
 mod histogram_data_point_to_time_series;
+mod interval;
+mod label_descriptor;
+mod monitored_resource;
+mod project_id;
+mod prometheus;
+mod routing;
+mod schema;
+mod self_metrics;
+#[cfg(feature = "spool")]
+mod spool;
+mod stats;
 mod to_f64;
+mod u64_policy;
+mod unique_identifier;
 mod utils;
 
 use itertools::Itertools;
-use opentelemetry_resourcedetector_gcp_rust::mapping::get_monitored_resource;
 
 use opentelemetry_sdk::{
     error::OTelSdkError,
@@ -15,48 +34,204 @@ use opentelemetry_sdk::{
     },
 };
 
-use rand::Rng;
+use self_metrics::{DescriptorOutcome, ExporterMetrics};
 use std::{
+    borrow::Cow,
     collections::{HashMap, HashSet},
     fmt::{Debug, Formatter},
-    sync::Arc,
+    sync::{Arc, RwLock},
     time::{Duration, SystemTime},
 };
-#[cfg(feature = "tokio")]
-use tokio::sync::RwLock;
+use tracing::Instrument;
 
-use utils::{get_data_points_attributes_keys, normalize_label_key};
+use utils::get_data_points_attributes_keys;
 
-use crate::exporter::utils::get_project_id;
+pub use builder::GCPMetricsExporterBuilder;
+#[cfg(any(test, feature = "test-utils"))]
+pub(crate) use builder::{validate_config, validate_metric_service_config};
+pub use client::CredentialsConfig;
+#[cfg(feature = "cli")]
+pub use descriptor_admin::{
+    MetricDescriptorAdmin, MetricDescriptorDiff, diff_metric_descriptors, read_metric_descriptors,
+    validate_prune_prefix,
+};
+#[cfg(test)]
+pub(crate) use dry_run::DryRunMetricService;
+pub use dry_run::{DryRunConfig, DryRunFormat, DryRunOutput};
+pub use error::{ExportErrorDetails, ExportErrorHandler, ExportOperation, GCPMetricsExportError};
+pub use label_descriptor::LabelDescriptorConfig;
+pub use monitored_resource::{
+    GcpResourceDetectorMapper, GenericNodeMapper, GenericTaskMapper, MonitoredResourceMapper,
+    MonitoredResourceResolver, MonitoredResourceResolverFn,
+};
+pub use project_id::{ProjectIdDetectionConfig, ProjectIdSource};
+pub use prometheus::{PrometheusConfig, PrometheusTargetMapper};
+pub use routing::{ProjectRouterFn, ProjectRouting};
+pub use schema::{MetricSchema, MetricSchemaEntry, MetricSchemaLabel, UndeclaredPolicy};
+#[cfg(feature = "spool")]
+pub use spool::SpoolConfig;
+pub use stats::{ExportOutcome, ExportStats, ExportStatsHandle};
+pub use u64_policy::U64Policy;
+pub use unique_identifier::UniqueIdentifierSource;
 
-pub(crate) const UNIQUE_IDENTIFIER_KEY: &str = "opentelemetry_id";
+/// Descriptor of a metric and how it matches the schema.
+type BuiltMetricDescriptor = Arc<(google_cloud_api::model::MetricDescriptor, Option<schema::SchemaMatch>)>;
 
 /// Implementation of Metrics Exporter to Google Cloud Monitoring.
 pub struct GCPMetricsExporter {
     prefix: String,
     project_id: String,
-    add_unique_identifier: bool,
-    unique_identifier: String,
+    unique_identifier: Option<unique_identifier::UniqueIdentifier>,
     metric_service: google_cloud_monitoring_v3::client::MetricService,
-    metric_descriptors: Arc<RwLock<HashMap<String, google_cloud_api::model::MetricDescriptor>>>,
-    custom_monitored_resource_data: Option<MonitoredResourceDataConfig>,
+    /// Created metric descriptors by (project id, metric type).
+    metric_descriptors: Arc<RwLock<HashMap<(String, String), google_cloud_api::model::MetricDescriptor>>>,
+    /// Descriptors of the exported metrics by metric name, see
+    /// [`GCPMetricsExporter::metric_descriptor`].
+    built_metric_descriptors: RwLock<HashMap<String, BuiltMetricDescriptor>>,
+    custom_monitored_resource: Option<google_cloud_api::model::MonitoredResource>,
+    /// Last exported resource and its mapped monitored resource, the resource
+    /// rarely changes between exports.
+    monitored_resource_cache: std::sync::Mutex<
+        Option<(
+            opentelemetry_sdk::Resource,
+            Option<google_cloud_api::model::MonitoredResource>,
+        )>,
+    >,
+    monitored_resource_resolvers: Vec<MonitoredResourceResolver>,
+    monitored_resource_mapper: Arc<dyn MonitoredResourceMapper>,
+    #[cfg(feature = "spool")]
+    spool: Option<spool::Spool>,
+    self_metrics: Arc<ExporterMetrics>,
+    on_error: Option<ExportErrorHandler>,
+    project_id_source: ProjectIdSource,
+    project_routing: ProjectRouting,
+    prometheus: Option<PrometheusConfig>,
+    skip_metric_descriptor_creation: bool,
+    schema: Option<schema::SchemaCheck>,
+    label_descriptors: Vec<LabelDescriptorConfig>,
+    cumulative: Option<cumulative::CumulativeTracker>,
+    counters_as_gauge: bool,
+    up_down_counters_as_cumulative: bool,
+    u64_conversion: u64_policy::U64Conversion,
+    temporality: Temporality,
+    /// Runtime the exporter was built on, exports from outside of a tokio
+    /// runtime (e.g. the SDK's thread based `PeriodicReader`) enter it while
+    /// they are polled.
+    #[cfg(feature = "tokio")]
+    runtime: Option<tokio::runtime::Handle>,
 }
 
 /// Configuration for the GCP metrics exporter.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct GCPMetricsExporterConfig {
     /// prefix: the prefix of the metric. It is "workload.googleapis.com" by
     ///     default if not specified.
     pub prefix: String,
-    /// project id of your Google Cloud project. It is get from GcpAuthorizer by default.
+    /// project id of your Google Cloud project. It is detected by default,
+    ///     see [`ProjectIdDetectionConfig`].
     pub project_id: Option<String>,
     /// add_unique_identifier: Add an identifier to each exporter metric. This
     ///     must be used when there exist two (or more) exporters that may
     ///     export to the same metric name within WRITE_INTERVAL seconds of
     ///     each other.
     pub add_unique_identifier: bool,
+    /// unique_identifier_key: Label key of the unique identifier,
+    ///     `opentelemetry_id` by default.
+    pub unique_identifier_key: String,
+    /// unique_identifier_source: Value of the unique identifier, a random id
+    ///     generated at `init` by default. See [`UniqueIdentifierSource`].
+    pub unique_identifier_source: UniqueIdentifierSource,
     /// custom_monitored_resource_data: Custom monitored resource data to be
     pub custom_monitored_resource_data: Option<MonitoredResourceDataConfig>,
+    /// monitored_resource_resolvers: Per metric monitored resources, they
+    ///     take precedence over `custom_monitored_resource_data` and the
+    ///     detected resource. See [`MonitoredResourceResolver`].
+    pub monitored_resource_resolvers: Vec<MonitoredResourceResolver>,
+    /// monitored_resource_mapper: Maps the OpenTelemetry resource to the
+    ///     monitored resource when `custom_monitored_resource_data` is not
+    ///     set, [`GcpResourceDetectorMapper`] by default.
+    pub monitored_resource_mapper: Arc<dyn MonitoredResourceMapper>,
+    /// check_monitored_resource_descriptors: Compares the labels of
+    ///     `custom_monitored_resource_data` and `monitored_resource_resolvers`
+    ///     with the monitored resource descriptors of the project at `init`.
+    ///     Without it only well known resource types are checked.
+    pub check_monitored_resource_descriptors: bool,
+    /// spool: Keep time series batches that failed with a retryable error on
+    ///     disk and replay them, in order, on the next successful export.
+    ///     Disabled by default.
+    #[cfg(feature = "spool")]
+    pub spool: Option<SpoolConfig>,
+    /// self_metrics_meter: Meter used to record metrics about the exporter
+    ///     itself: export duration, time series sent/failed/dropped,
+    ///     metric descriptor create outcomes and retries. Disabled by default.
+    pub self_metrics_meter: Option<opentelemetry::metrics::Meter>,
+    /// on_error: Called with every export failure, including the ones the
+    ///     exporter recovers from (skipped metric, spooled batch).
+    pub on_error: Option<ExportErrorHandler>,
+    /// dry_run: Write the requests as JSON instead of sending them, without
+    ///     loading credentials or detecting the project id. Disabled by default.
+    pub dry_run: Option<DryRunConfig>,
+    /// endpoint: Cloud Monitoring API endpoint, e.g. `http://localhost:8080`
+    ///     for a local stand-in. `https://monitoring.googleapis.com` by default.
+    pub endpoint: Option<String>,
+    /// credentials: How the client authenticates. Application default
+    ///     credentials by default.
+    pub credentials: CredentialsConfig,
+    /// insecure: Allow a plain text `http://` endpoint. It only lifts the
+    ///     `https://` check of `endpoint`, the TLS settings of https
+    ///     endpoints (certificate and host name verification) do not change.
+    ///     Disabled by default.
+    pub insecure: bool,
+    /// impersonate_service_account: Email of a service account to impersonate
+    ///     with `credentials`, needs `roles/iam.serviceAccountTokenCreator` on it.
+    pub impersonate_service_account: Option<String>,
+    /// quota_project_id: Project billed for the API calls, sent as the
+    ///     `x-goog-user-project` header.
+    pub quota_project_id: Option<String>,
+    /// project_id_detection: Sources tried when `project_id` is not set,
+    ///     see [`ProjectIdDetectionConfig`].
+    pub project_id_detection: ProjectIdDetectionConfig,
+    /// project_routing: Picks the project of each time series, everything
+    ///     goes to `project_id` by default. See [`ProjectRouting`].
+    pub project_routing: ProjectRouting,
+    /// prometheus: Writes metrics like Managed Service for Prometheus, to
+    ///     query them with PromQL. `prefix` and `monitored_resource_mapper`
+    ///     are not used. See [`PrometheusConfig`].
+    pub prometheus: Option<PrometheusConfig>,
+    /// skip_metric_descriptor_creation: Does not call CreateMetricDescriptor,
+    ///     Cloud Monitoring creates the descriptors of `custom.googleapis.com`
+    ///     and `workload.googleapis.com` metrics on the first write. Only
+    ///     `monitoring.timeSeries.create` is needed then, but label and
+    ///     metric descriptions are not set.
+    pub skip_metric_descriptor_creation: bool,
+    /// schema: Declared metrics, used instead of the descriptors inferred
+    ///     from the instruments. Undeclared metrics, labels and monitored
+    ///     resource types are handled as set in the schema. See [`MetricSchema`].
+    pub schema: Option<MetricSchema>,
+    /// label_descriptors: Descriptions and value types of the metric labels,
+    ///     including the `opentelemetry_id` unique identifier. Labels are
+    ///     strings without a description by default. See [`LabelDescriptorConfig`].
+    pub label_descriptors: Vec<LabelDescriptorConfig>,
+    /// handle_cumulative_resets: Keeps the last interval of every cumulative
+    ///     time series. When the SDK restarts an aggregation or a counter goes
+    ///     down, the new interval starts after the last written point instead
+    ///     of being rejected by Cloud Monitoring. Points written out of order
+    ///     are dropped. Enabled by default.
+    pub handle_cumulative_resets: bool,
+    /// counters_as_gauge: Writes monotonic counters as GAUGE metrics of the
+    ///     current total, without a start time. CUMULATIVE by default.
+    pub counters_as_gauge: bool,
+    /// up_down_counters_as_cumulative: Writes UpDownCounters as CUMULATIVE
+    ///     metrics starting when the SDK started aggregating them, e.g. for
+    ///     the rate aligners. GAUGE by default.
+    pub up_down_counters_as_cumulative: bool,
+    /// u64_policy: How u64 sums and gauges above `i64::MAX` are written,
+    ///     saturated INT64 values by default. See [`U64Policy`].
+    pub u64_policy: U64Policy,
+    /// temporality: Temporality the SDK aggregates the metrics with.
+    ///     Cumulative by default. With Delta, each CUMULATIVE point starts
+    ///     when the previous export ended and holds the change since then.
+    pub temporality: Temporality,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -75,26 +250,160 @@ impl Default for GCPMetricsExporterConfig {
             prefix: "workload.googleapis.com".to_string(),
             project_id: None,
             add_unique_identifier: false,
+            unique_identifier_key: unique_identifier::DEFAULT_UNIQUE_IDENTIFIER_KEY.to_string(),
+            unique_identifier_source: UniqueIdentifierSource::default(),
             custom_monitored_resource_data: None,
+            monitored_resource_resolvers: Vec::new(),
+            monitored_resource_mapper: Arc::new(GcpResourceDetectorMapper),
+            check_monitored_resource_descriptors: false,
+            #[cfg(feature = "spool")]
+            spool: None,
+            self_metrics_meter: None,
+            on_error: None,
+            dry_run: None,
+            endpoint: None,
+            credentials: CredentialsConfig::default(),
+            insecure: false,
+            impersonate_service_account: None,
+            quota_project_id: None,
+            project_id_detection: ProjectIdDetectionConfig::default(),
+            project_routing: ProjectRouting::default(),
+            prometheus: None,
+            skip_metric_descriptor_creation: false,
+            schema: None,
+            label_descriptors: Vec::new(),
+            handle_cumulative_resets: true,
+            counters_as_gauge: false,
+            up_down_counters_as_cumulative: false,
+            u64_policy: U64Policy::default(),
+            temporality: Temporality::Cumulative,
         }
     }
 }
 
+/// Mappers, resolvers, routers, error handlers and dry-run writers are equal
+/// when they are clones of the same one. The self metrics meter is not
+/// compared.
+impl PartialEq for GCPMetricsExporterConfig {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            prefix,
+            project_id,
+            add_unique_identifier,
+            unique_identifier_key,
+            unique_identifier_source,
+            custom_monitored_resource_data,
+            monitored_resource_resolvers,
+            monitored_resource_mapper,
+            check_monitored_resource_descriptors,
+            #[cfg(feature = "spool")]
+            spool,
+            self_metrics_meter: _,
+            on_error,
+            dry_run,
+            endpoint,
+            credentials,
+            insecure,
+            impersonate_service_account,
+            quota_project_id,
+            project_id_detection,
+            project_routing,
+            prometheus,
+            skip_metric_descriptor_creation,
+            schema,
+            label_descriptors,
+            handle_cumulative_resets,
+            counters_as_gauge,
+            up_down_counters_as_cumulative,
+            u64_policy,
+            temporality,
+        } = self;
+        #[cfg(feature = "spool")]
+        if *spool != other.spool {
+            return false;
+        }
+        *prefix == other.prefix
+            && *project_id == other.project_id
+            && *add_unique_identifier == other.add_unique_identifier
+            && *unique_identifier_key == other.unique_identifier_key
+            && *unique_identifier_source == other.unique_identifier_source
+            && *custom_monitored_resource_data == other.custom_monitored_resource_data
+            && *monitored_resource_resolvers == other.monitored_resource_resolvers
+            && Arc::ptr_eq(monitored_resource_mapper, &other.monitored_resource_mapper)
+            && *check_monitored_resource_descriptors == other.check_monitored_resource_descriptors
+            && *on_error == other.on_error
+            && *dry_run == other.dry_run
+            && *endpoint == other.endpoint
+            && *credentials == other.credentials
+            && *insecure == other.insecure
+            && *impersonate_service_account == other.impersonate_service_account
+            && *quota_project_id == other.quota_project_id
+            && *project_id_detection == other.project_id_detection
+            && *project_routing == other.project_routing
+            && *prometheus == other.prometheus
+            && *skip_metric_descriptor_creation == other.skip_metric_descriptor_creation
+            && *schema == other.schema
+            && *label_descriptors == other.label_descriptors
+            && *handle_cumulative_resets == other.handle_cumulative_resets
+            && *counters_as_gauge == other.counters_as_gauge
+            && *up_down_counters_as_cumulative == other.up_down_counters_as_cumulative
+            && *u64_policy == other.u64_policy
+            && *temporality == other.temporality
+    }
+}
+
+impl Eq for GCPMetricsExporterConfig {}
+
 impl GCPMetricsExporter {
     pub(crate) fn new(
         metric_service: google_cloud_monitoring_v3::client::MetricService,
         project_id: String,
         config: GCPMetricsExporterConfig,
     ) -> Self {
-        let my_rundom = format!("{:08x}", rand::rng().random_range(0..u32::MAX));
+        let unique_identifier = unique_identifier::UniqueIdentifier::new(&config);
+        let schema = config.schema.map(|schema| {
+            schema::SchemaCheck::new(
+                schema,
+                unique_identifier
+                    .as_ref()
+                    .map(|unique_identifier| unique_identifier.key.clone()),
+            )
+        });
         Self {
             prefix: config.prefix,
-            add_unique_identifier: config.add_unique_identifier,
-            project_id: project_id,
-            unique_identifier: my_rundom,
+            project_id,
+            unique_identifier,
             metric_service,
             metric_descriptors: Arc::new(RwLock::new(HashMap::new())),
-            custom_monitored_resource_data: config.custom_monitored_resource_data,
+            built_metric_descriptors: RwLock::new(HashMap::new()),
+            custom_monitored_resource: config.custom_monitored_resource_data.map(|data| {
+                google_cloud_api::model::MonitoredResource::new()
+                    .set_type(data.r#type)
+                    .set_labels(data.labels)
+            }),
+            monitored_resource_cache: std::sync::Mutex::new(None),
+            monitored_resource_resolvers: config.monitored_resource_resolvers,
+            monitored_resource_mapper: match config.prometheus {
+                Some(_) => Arc::new(PrometheusTargetMapper),
+                None => config.monitored_resource_mapper,
+            },
+            #[cfg(feature = "spool")]
+            spool: config.spool.map(spool::Spool::new),
+            self_metrics: Arc::new(ExporterMetrics::new(config.self_metrics_meter.as_ref())),
+            on_error: config.on_error,
+            project_id_source: ProjectIdSource::Config,
+            project_routing: config.project_routing,
+            prometheus: config.prometheus,
+            skip_metric_descriptor_creation: config.skip_metric_descriptor_creation,
+            schema,
+            label_descriptors: config.label_descriptors,
+            cumulative: config.handle_cumulative_resets.then(cumulative::CumulativeTracker::new),
+            counters_as_gauge: config.counters_as_gauge,
+            up_down_counters_as_cumulative: config.up_down_counters_as_cumulative,
+            u64_conversion: u64_policy::U64Conversion::new(config.u64_policy),
+            temporality: config.temporality,
+            #[cfg(feature = "tokio")]
+            runtime: tokio::runtime::Handle::try_current().ok(),
         }
     }
 }
@@ -103,38 +412,51 @@ impl GCPMetricsExporter {
 pub enum GCPMetricsExporterInitError {
     #[error("could not init gcp credentials")]
     InitCredentials(#[source] google_cloud_gax::client_builder::Error),
+    #[error("could not load gcp credentials")]
+    LoadCredentials(#[source] Box<dyn std::error::Error + Send + Sync>),
     #[error("could not detect project id automatically")]
     ProjectIdDedection(#[source] std::io::Error),
+    #[error("could not open dry run output")]
+    DryRunOutput(#[source] std::io::Error),
+    #[error("invalid exporter config: {0}")]
+    InvalidConfig(String),
+    #[error("could not load metric schema")]
+    LoadSchema(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("could not get monitored resource descriptor")]
+    MonitoredResourceDescriptor(#[source] google_cloud_gax::error::Error),
 }
 
 impl GCPMetricsExporter {
     pub async fn init(config: GCPMetricsExporterConfig) -> Result<GCPMetricsExporter, GCPMetricsExporterInitError> {
-        let client = google_cloud_monitoring_v3::client::MetricService::builder()
-            .build()
-            .await
-            .map_err(GCPMetricsExporterInitError::InitCredentials)?;
+        Self::builder().with_config(config).build().await
+    }
 
-        let custom_monitored_resource_data_project_id = config
-            .custom_monitored_resource_data
-            .as_ref()
-            .and_then(|v| v.labels.get("project_id").cloned());
+    pub fn builder() -> GCPMetricsExporterBuilder {
+        GCPMetricsExporterBuilder::default()
+    }
+}
 
-        let project_id = config.project_id.clone().or(custom_monitored_resource_data_project_id);
+impl GCPMetricsExporter {
+    /// Project the metrics are written to.
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
 
-        let project_id = match project_id {
-            Some(project_id) => project_id,
-            None => match get_project_id().await {
-                Ok(proj) => proj,
-                Err(err) => {
-                    return Err(GCPMetricsExporterInitError::ProjectIdDedection(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        err,
-                    )));
-                }
-            },
-        };
+    /// Where [`GCPMetricsExporter::project_id`] comes from.
+    pub fn project_id_source(&self) -> ProjectIdSource {
+        self.project_id_source
+    }
+
+    /// Returns a snapshot of the export statistics: last export time and
+    /// outcome, last error per category and cumulative counters.
+    pub fn stats(&self) -> ExportStats {
+        self.self_metrics.stats()
+    }
 
-        Ok(GCPMetricsExporter::new(client, project_id, config))
+    /// Returns a handle to read [`GCPMetricsExporter::stats`] after the
+    /// exporter has been moved into a metric reader.
+    pub fn stats_handle(&self) -> ExportStatsHandle {
+        ExportStatsHandle::new(self.self_metrics.clone())
     }
 }
 
@@ -145,26 +467,42 @@ impl Debug for GCPMetricsExporter {
 }
 
 impl GCPMetricsExporter {
-    /// We can map Metric to MetricDescriptor using Metric.name or
-    /// MetricDescriptor.type. We create the MetricDescriptor if it doesn't
-    /// exist already and cache it. Note that recreating MetricDescriptors is
-    /// a no-op if it already exists.
-    ///
-    /// :param record:
-    /// :return:
-    async fn get_metric_descriptor(
-        &self,
-        metric: &OpentelemetrySdkMetric,
-    ) -> Result<Option<google_cloud_api::model::MetricDescriptor>, OTelSdkError> {
-        let descriptor_type = format!("{}/{}", self.prefix, metric.name());
-        let cached_metric_descriptor = {
-            let metric_descriptors = self.metric_descriptors.read().await;
-            metric_descriptors.get(&descriptor_type).cloned()
-        };
-        if let Some(cached_metric_descriptor) = cached_metric_descriptor {
-            return Ok(Some(cached_metric_descriptor));
+    /// Descriptor of `metric` with the schema and Prometheus naming applied,
+    /// built on the first export of the metric and cached by name.
+    fn metric_descriptor(&self, metric: &OpentelemetrySdkMetric) -> BuiltMetricDescriptor {
+        if let Some(built) = self
+            .built_metric_descriptors
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(metric.name())
+        {
+            return built.clone();
+        }
+        let mut descriptor = self.build_metric_descriptor(metric);
+        let schema_match = self
+            .schema
+            .as_ref()
+            .map(|schema| schema.apply(metric.name(), &self.prefix, &mut descriptor));
+        if let Some(prometheus) = &self.prometheus {
+            prometheus.apply(
+                metric.name(),
+                metric.unit(),
+                interval::is_monotonic(metric.data()),
+                &mut descriptor,
+            );
         }
+        let built = Arc::new((descriptor, schema_match));
+        self.built_metric_descriptors
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(metric.name().to_string(), built.clone());
+        built
+    }
 
+    /// We can map Metric to MetricDescriptor using Metric.name or
+    /// MetricDescriptor.type.
+    fn build_metric_descriptor(&self, metric: &OpentelemetrySdkMetric) -> google_cloud_api::model::MetricDescriptor {
+        let descriptor_type = format!("{}/{}", self.prefix, metric.name());
         let unit = metric.unit().to_string();
         let mut descriptor = google_cloud_api::model::MetricDescriptor::new()
             .set_type(descriptor_type.clone())
@@ -172,19 +510,24 @@ impl GCPMetricsExporter {
             .set_description(metric.description().to_string())
             .set_unit(unit);
 
-        let seen_keys: HashSet<String> = get_data_points_attributes_keys(metric.data());
+        let seen_keys: HashSet<&str> = get_data_points_attributes_keys(metric.data());
 
         for key in &seen_keys {
-            descriptor
-                .labels
-                .push(google_cloud_api::model::LabelDescriptor::new().set_key(normalize_label_key(key)));
+            descriptor.labels.push(label_descriptor::label_descriptor(
+                &self.label_descriptors,
+                metric.name(),
+                key,
+            ));
         }
 
-        // todo add unique identifier
-        if self.add_unique_identifier {
+        if let Some(unique_identifier) = &self.unique_identifier {
             descriptor
                 .labels
-                .push(google_cloud_api::model::LabelDescriptor::new().set_key(UNIQUE_IDENTIFIER_KEY.to_string()));
+                .push(label_descriptor::unique_identifier_label_descriptor(
+                    &self.label_descriptors,
+                    metric.name(),
+                    &unique_identifier.key,
+                ));
         }
 
         match metric.data() {
@@ -198,11 +541,11 @@ impl GCPMetricsExporter {
                     descriptor.value_type = google_cloud_api::model::metric_descriptor::ValueType::Distribution;
                 }
                 MetricData::Sum(m) => {
-                    descriptor.metric_kind = if m.is_monotonic() {
-                        google_cloud_api::model::metric_descriptor::MetricKind::Cumulative
-                    } else {
-                        google_cloud_api::model::metric_descriptor::MetricKind::Gauge
-                    };
+                    descriptor.metric_kind = interval::sum_metric_kind(
+                        m.is_monotonic(),
+                        self.counters_as_gauge,
+                        self.up_down_counters_as_cumulative,
+                    );
                     descriptor.value_type = google_cloud_api::model::metric_descriptor::ValueType::Double;
                 }
                 MetricData::Gauge(_) => {
@@ -220,11 +563,11 @@ impl GCPMetricsExporter {
                     descriptor.value_type = google_cloud_api::model::metric_descriptor::ValueType::Distribution;
                 }
                 MetricData::Sum(m) => {
-                    descriptor.metric_kind = if m.is_monotonic() {
-                        google_cloud_api::model::metric_descriptor::MetricKind::Cumulative
-                    } else {
-                        google_cloud_api::model::metric_descriptor::MetricKind::Gauge
-                    };
+                    descriptor.metric_kind = interval::sum_metric_kind(
+                        m.is_monotonic(),
+                        self.counters_as_gauge,
+                        self.up_down_counters_as_cumulative,
+                    );
                     descriptor.value_type = google_cloud_api::model::metric_descriptor::ValueType::Int64;
                 }
                 MetricData::Gauge(_) => {
@@ -242,22 +585,48 @@ impl GCPMetricsExporter {
                     descriptor.value_type = google_cloud_api::model::metric_descriptor::ValueType::Distribution;
                 }
                 MetricData::Sum(m) => {
-                    descriptor.metric_kind = if m.is_monotonic() {
-                        google_cloud_api::model::metric_descriptor::MetricKind::Cumulative
-                    } else {
-                        google_cloud_api::model::metric_descriptor::MetricKind::Gauge
-                    };
-                    descriptor.value_type = google_cloud_api::model::metric_descriptor::ValueType::Int64;
+                    descriptor.metric_kind = interval::sum_metric_kind(
+                        m.is_monotonic(),
+                        self.counters_as_gauge,
+                        self.up_down_counters_as_cumulative,
+                    );
+                    descriptor.value_type = self.u64_conversion.policy().value_type();
                 }
                 MetricData::Gauge(_) => {
                     descriptor.metric_kind = google_cloud_api::model::metric_descriptor::MetricKind::Gauge;
-                    descriptor.value_type = google_cloud_api::model::metric_descriptor::ValueType::Int64;
+                    descriptor.value_type = self.u64_conversion.policy().value_type();
                 }
             },
         }
+        descriptor
+    }
+
+    /// We create the MetricDescriptor in `project_id` if it doesn't exist
+    /// already and cache it. Note that recreating MetricDescriptors is a
+    /// no-op if it already exists.
+    async fn ensure_metric_descriptor(
+        &self,
+        project_id: &str,
+        descriptor: &google_cloud_api::model::MetricDescriptor,
+    ) -> Result<(), GCPMetricsExportError> {
+        // Cloud Monitoring creates them on the first write, like Managed Service
+        // for Prometheus does.
+        if self.skip_metric_descriptor_creation || self.prometheus.is_some() {
+            return Ok(());
+        }
+        let cache_key = (project_id.to_string(), descriptor.r#type.clone());
+        if self
+            .metric_descriptors
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .contains_key(&cache_key)
+        {
+            return Ok(());
+        }
+        let descriptor_type = descriptor.r#type.clone();
 
         let req = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
-            .set_name(format!("projects/{}", self.project_id.clone()))
+            .set_name(format!("projects/{}", project_id))
             .set_metric_descriptor(descriptor.clone());
 
         match self
@@ -265,42 +634,74 @@ impl GCPMetricsExporter {
             .create_metric_descriptor()
             .with_request(req)
             .send()
+            .instrument(tracing::debug_span!(
+                "gcp_metrics_exporter.create_metric_descriptor",
+                project = project_id,
+                metric_type = descriptor_type.as_str()
+            ))
             .await
         {
-            Ok(_) => {}
+            Ok(_) => {
+                self.self_metrics.record_descriptor(DescriptorOutcome::Created);
+            }
             Err(err) => {
+                let export_err = GCPMetricsExportError::from_rpc(
+                    ExportOperation::CreateMetricDescriptor,
+                    &err,
+                    vec![descriptor_type.clone()],
+                    0,
+                );
                 match err.status() {
                     Some(status) if status.code == google_cloud_gax::error::rpc::Code::AlreadyExists => {
                         // Metric descriptor already exists, this is fine.
-                        let mut metric_descriptors = self.metric_descriptors.write().await;
-                        metric_descriptors.insert(descriptor_type, descriptor.clone());
-                        return Ok(Some(descriptor));
+                        self.self_metrics.record_descriptor(DescriptorOutcome::AlreadyExists);
+                        let mut metric_descriptors = self
+                            .metric_descriptors
+                            .write()
+                            .unwrap_or_else(|poisoned| poisoned.into_inner());
+                        metric_descriptors.insert(cache_key, descriptor.clone());
+                        self.self_metrics
+                            .set_cached_metric_descriptors(metric_descriptors.len());
+                        return Ok(());
                     }
                     Some(status) if status.code == google_cloud_gax::error::rpc::Code::PermissionDenied => {
-                        // Metric descriptor already exists, this is fine.
-                        let str_err = format!(
-                            "GCPMetricsExporter: PermissionDenied need access with role: `roles/monitoring.metricWriter` or permissions: `monitoring.metricDescriptors.create`, `monitoring.timeSeries.create`"
-                        );
-                        tracing::warn!("{}", str_err);
-                        return Err(OTelSdkError::InternalFailure(str_err));
+                        self.self_metrics.record_descriptor(DescriptorOutcome::PermissionDenied);
+                        tracing::warn!("GCPMetricsExporter: {}", export_err);
+                        self.report_error(&export_err);
+                        return Err(export_err);
                     }
                     _ => {
-                        // Other errors are logged and we return None.
+                        // Other errors are reported and the metric is skipped for this export.
+                        self.self_metrics.record_descriptor(DescriptorOutcome::Error);
                     }
                 }
                 tracing::debug!("GCPMetricsExporter: Cant create metric descriptor: {:?}", err);
-                return Ok(None);
+                self.report_error(&export_err);
+                return Err(export_err);
             }
         }
 
         {
-            let mut metric_descriptors = self.metric_descriptors.write().await;
-            metric_descriptors.insert(descriptor_type, descriptor.clone());
+            let mut metric_descriptors = self
+                .metric_descriptors
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            metric_descriptors.insert(cache_key, descriptor.clone());
+            self.self_metrics
+                .set_cached_metric_descriptors(metric_descriptors.len());
+        }
+        Ok(())
+    }
+
+    /// Records the failure in the stats and hands it to the `on_error` callback.
+    fn report_error(&self, err: &GCPMetricsExportError) {
+        self.self_metrics.record_error(err.clone());
+        if let Some(on_error) = &self.on_error {
+            on_error.call(err);
         }
-        Ok(Some(descriptor))
     }
 
-    async fn exec_export(&self, metrics: &ResourceMetrics) -> Result<(), OTelSdkError> {
+    async fn exec_export(&self, metrics: &ResourceMetrics) -> Result<(), GCPMetricsExportError> {
         // // println!("export: {:#?}", metrics);
         // let proto_message: ExportMetricsServiceRequest = (&*metrics).into();
         // // println!("export: {}", serde_json::to_string_pretty(&proto_message).unwrap());
@@ -308,27 +709,31 @@ impl GCPMetricsExporter {
         // use std::io::Write;
         // let mut file = std::fs::File::create("metrics.txt").unwrap();
         // file.write_all(format!("{:#?}", metrics).as_bytes()).unwrap();
-        let monitored_resource_data = match self.custom_monitored_resource_data.clone() {
-            Some(custom_monitored_resource_data) => Some(
-                google_cloud_api::model::MonitoredResource::new()
-                    .set_type(custom_monitored_resource_data.r#type)
-                    .set_labels(custom_monitored_resource_data.labels),
-            ),
-            None => get_monitored_resource(metrics.resource()).map(|v| {
-                google_cloud_api::model::MonitoredResource::new()
-                    .set_type(v.r#type)
-                    .set_labels(v.labels)
-            }),
-        };
+        let monitored_resource_data = self.monitored_resource(metrics.resource());
+        let unique_identifier_value = self
+            .unique_identifier
+            .as_ref()
+            .map(|unique_identifier| unique_identifier.value(metrics.resource()));
+        let unique_identifier = self
+            .unique_identifier
+            .as_ref()
+            .zip(unique_identifier_value.as_deref())
+            .map(|(unique_identifier, value)| (unique_identifier.key.as_str(), value));
+        if let Some(cumulative) = &self.cumulative {
+            cumulative.evict_stale();
+        }
 
-        let mut all_series = Vec::<google_cloud_monitoring_v3::model::TimeSeries>::new();
+        // Failures that do not stop the export are returned once everything else was sent.
+        let mut first_error: Option<GCPMetricsExportError> = None;
+        // Time series by destination project, in the order projects are first seen.
+        let mut project_series = Vec::<(String, Vec<google_cloud_monitoring_v3::model::TimeSeries>)>::new();
         for scope_metric in metrics.scope_metrics() {
             for metric in scope_metric.metrics() {
-                let descriptor = if let Some(descriptor) = self.get_metric_descriptor(metric).await? {
-                    descriptor
-                } else {
-                    continue;
-                };
+                let built_descriptor = self.metric_descriptor(metric);
+                let (descriptor, schema_match) = &*built_descriptor;
+                // only copied when a resolver removes labels
+                let mut descriptor = Cow::Borrowed(descriptor);
+                let mut all_series = Vec::<google_cloud_monitoring_v3::model::TimeSeries>::new();
                 match metric.data() {
                     AggregatedMetrics::F64(v) => match v {
                         MetricData::Histogram(m) => {
@@ -338,9 +743,7 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.as_str(),
+                                    unique_identifier,
                                 ));
                             }
                         }
@@ -351,9 +754,7 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.as_str(),
+                                    unique_identifier,
                                 ));
                             }
                         }
@@ -364,9 +765,7 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.clone(),
+                                    unique_identifier,
                                 ));
                             }
                         }
@@ -377,9 +776,7 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.clone(),
+                                    unique_identifier,
                                 ));
                             }
                         }
@@ -392,9 +789,7 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.as_str(),
+                                    unique_identifier,
                                 ));
                             }
                         }
@@ -405,9 +800,7 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.as_str(),
+                                    unique_identifier,
                                 ));
                             }
                         }
@@ -418,9 +811,8 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.clone(),
+                                    unique_identifier,
+                                    &self.u64_conversion,
                                 ));
                             }
                         }
@@ -431,9 +823,8 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.clone(),
+                                    unique_identifier,
+                                    &self.u64_conversion,
                                 ));
                             }
                         }
@@ -446,9 +837,7 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.as_str(),
+                                    unique_identifier,
                                 ));
                             }
                         }
@@ -459,9 +848,7 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.as_str(),
+                                    unique_identifier,
                                 ));
                             }
                         }
@@ -472,9 +859,8 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.clone(),
+                                    unique_identifier,
+                                    &self.u64_conversion,
                                 ));
                             }
                         }
@@ -485,65 +871,344 @@ impl GCPMetricsExporter {
                                     &m.start_time(),
                                     &m.time(),
                                     &descriptor,
-                                    &monitored_resource_data,
-                                    self.add_unique_identifier,
-                                    self.unique_identifier.clone(),
+                                    unique_identifier,
+                                    &self.u64_conversion,
                                 ));
                             }
                         }
                     },
                 }
+                if !self.monitored_resource_resolvers.is_empty() {
+                    monitored_resource::resolve_monitored_resources(
+                        &self.monitored_resource_resolvers,
+                        metric.name(),
+                        descriptor.to_mut(),
+                        &mut all_series,
+                    );
+                }
+                // A TimeSeries owns its resource and CreateTimeSeriesRequest has
+                // none shared by its series, every series is sent with a copy.
+                if let Some(resource) = &monitored_resource_data {
+                    for time_series in all_series
+                        .iter_mut()
+                        .filter(|time_series| time_series.resource.is_none())
+                    {
+                        time_series.resource = Some(resource.clone());
+                    }
+                }
+                if let (Some(schema), Some(schema_match)) = (&self.schema, *schema_match) {
+                    let count = all_series.len();
+                    schema.check_time_series(metric.name(), schema_match, &mut all_series);
+                    self.self_metrics.add_dropped(count - all_series.len());
+                    if all_series.is_empty() {
+                        continue;
+                    }
+                }
+                if let (Some(cumulative), false) = (&self.cumulative, all_series.is_empty()) {
+                    let check = cumulative.check_time_series(
+                        &mut all_series,
+                        interval::is_monotonic(metric.data()),
+                        interval::temporality(metric.data()),
+                    );
+                    self.self_metrics.add_cumulative_resets(check.resets);
+                    self.self_metrics.add_dropped(check.dropped);
+                    if all_series.is_empty() {
+                        continue;
+                    }
+                }
+
+                for (project_id, series) in self.route_time_series(metrics.resource(), all_series) {
+                    if let Err(err) = self.ensure_metric_descriptor(&project_id, &descriptor).await {
+                        self.self_metrics.add_dropped(series.len());
+                        if let GCPMetricsExportError::PermissionDenied(_) = err {
+                            return Err(err);
+                        }
+                        first_error.get_or_insert(err);
+                        continue;
+                    }
+                    match project_series.iter_mut().find(|(id, _)| *id == project_id) {
+                        Some((_, project_series)) => project_series.extend(series),
+                        None => project_series.push((project_id, series)),
+                    }
+                }
             }
         }
         // println!("all_series len: {}", all_series.len());
-        let chunked_all_series: Vec<Vec<google_cloud_monitoring_v3::model::TimeSeries>> = all_series
+        let chunked_all_series: Vec<(String, Vec<google_cloud_monitoring_v3::model::TimeSeries>)> = project_series
             .into_iter()
-            .chunks(200)
-            .into_iter()
-            .map(|chunk| chunk.collect())
+            .flat_map(|(project_id, series)| {
+                series
+                    .into_iter()
+                    .chunks(200)
+                    .into_iter()
+                    .map(|chunk| (project_id.clone(), chunk.collect()))
+                    .collect::<Vec<_>>()
+            })
             .collect();
         // todo add more usefull error handling and retry
-        let project_id = self.project_id.clone();
-        for chunk in chunked_all_series {
+        #[cfg(feature = "spool")]
+        let mut spool_backlog = self.replay_spool().await;
+        for (project_id, chunk) in chunked_all_series {
+            let chunk_len = chunk.len();
+            let metric_types = time_series_metric_types(&chunk);
             let req = google_cloud_monitoring_v3::model::CreateTimeSeriesRequest::new()
                 .set_name(format!("projects/{}", project_id))
-                .set_time_series(chunk.clone());
+                .set_time_series(chunk);
+
+            // Older batches are still waiting on disk, keep the order of points.
+            #[cfg(feature = "spool")]
+            if spool_backlog {
+                self.spool_request(&req).await;
+                continue;
+            }
 
-            match self.metric_service.create_time_series().with_request(req).send().await {
-                Ok(_) => {}
+            // Only kept to be spooled if the call fails.
+            #[cfg(feature = "spool")]
+            let spooled_req = self.spool.as_ref().map(|_| req.clone());
+            match self.send_time_series(req).await {
+                Ok(_) => {
+                    self.self_metrics.add_sent(chunk_len);
+                }
                 Err(err) => {
-                    match err.status() {
-                        Some(status) if status.code == google_cloud_gax::error::rpc::Code::PermissionDenied => {
-                            let str_err = format!(
-                                "GCPMetricsExporter: PermissionDenied need access with role: `roles/monitoring.metricWriter` or permissions: `monitoring.metricDescriptors.create`, `monitoring.timeSeries.create`"
-                            );
-                            tracing::warn!("{}", str_err);
-                            return Err(OTelSdkError::InternalFailure(str_err));
-                        }
-                        _ => {}
+                    self.self_metrics.add_failed(chunk_len);
+                    let export_err = GCPMetricsExportError::from_rpc(
+                        ExportOperation::CreateTimeSeries,
+                        &err,
+                        metric_types,
+                        chunk_len,
+                    );
+                    self.report_error(&export_err);
+                    if let GCPMetricsExportError::PermissionDenied(_) = export_err {
+                        self.self_metrics.add_dropped(chunk_len);
+                        tracing::warn!("GCPMetricsExporter: {}", export_err);
+                        return Err(export_err);
                     }
                     tracing::debug!("GCPMetricsExporter: Cant send time series: {:?}", err);
+                    #[cfg(feature = "spool")]
+                    if let (Some(spooled_req), true) = (&spooled_req, spool::is_retryable(&err)) {
+                        self.spool_request(spooled_req).await;
+                        spool_backlog = true;
+                        continue;
+                    }
+                    self.self_metrics.add_dropped(chunk_len);
+                    first_error.get_or_insert(export_err);
                     continue;
                 }
             }
         }
-        Ok(())
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Monitored resource of the time series of `resource`, the mapped
+    /// resource is cached until the resource changes.
+    fn monitored_resource(
+        &self,
+        resource: &opentelemetry_sdk::Resource,
+    ) -> Option<google_cloud_api::model::MonitoredResource> {
+        if let Some(custom_monitored_resource) = &self.custom_monitored_resource {
+            return Some(custom_monitored_resource.clone());
+        }
+        let mut cache = self
+            .monitored_resource_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((cached_resource, monitored_resource)) = cache.as_ref() {
+            if cached_resource == resource {
+                return monitored_resource.clone();
+            }
+        }
+        let monitored_resource = self.monitored_resource_mapper.map(resource).map(|data| {
+            google_cloud_api::model::MonitoredResource::new()
+                .set_type(data.r#type)
+                .set_labels(data.labels)
+        });
+        *cache = Some((resource.clone(), monitored_resource.clone()));
+        monitored_resource
+    }
+
+    /// Groups the time series of one metric by destination project, see
+    /// [`ProjectRouting`]. A metric without time series still gets its
+    /// descriptor in the exporter project.
+    fn route_time_series(
+        &self,
+        resource: &opentelemetry_sdk::Resource,
+        all_series: Vec<google_cloud_monitoring_v3::model::TimeSeries>,
+    ) -> Vec<(String, Vec<google_cloud_monitoring_v3::model::TimeSeries>)> {
+        if self.project_routing.is_exporter_project() || all_series.is_empty() {
+            return vec![(self.project_id.clone(), all_series)];
+        }
+        let mut routed = Vec::<(String, Vec<google_cloud_monitoring_v3::model::TimeSeries>)>::new();
+        for time_series in all_series {
+            let project_id = self
+                .project_routing
+                .route(resource, &time_series)
+                .unwrap_or_else(|| self.project_id.clone());
+            match routed.iter_mut().find(|(id, _)| *id == project_id) {
+                Some((_, series)) => series.push(time_series),
+                None => routed.push((project_id, vec![time_series])),
+            }
+        }
+        routed
+    }
+
+    async fn send_time_series(
+        &self,
+        req: google_cloud_monitoring_v3::model::CreateTimeSeriesRequest,
+    ) -> google_cloud_gax::Result<()> {
+        let span = tracing::debug_span!(
+            "gcp_metrics_exporter.create_time_series",
+            project = req.name.as_str(),
+            time_series = req.time_series.len()
+        );
+        self.metric_service
+            .create_time_series()
+            .with_request(req)
+            .send()
+            .instrument(span)
+            .await
+            .map(|_| ())
     }
+
+    /// Sends the batches kept on disk, oldest first. Returns `true` when some
+    /// of them are still waiting because Cloud Monitoring is not reachable yet.
+    #[cfg(feature = "spool")]
+    async fn replay_spool(&self) -> bool {
+        let Some(spool) = &self.spool else {
+            return false;
+        };
+        let pending = match spool.pending().await {
+            Ok(pending) => pending,
+            Err(err) => {
+                tracing::warn!("GCPMetricsExporter: Cant read spool: {:?}", err);
+                self.report_error(&GCPMetricsExportError::spool(&err));
+                return false;
+            }
+        };
+        for entry in pending {
+            let req = match spool.load(&entry).await {
+                Ok(Some(req)) => req,
+                Ok(None) => {
+                    self.remove_spooled(spool, &entry).await;
+                    continue;
+                }
+                Err(err) => {
+                    tracing::warn!("GCPMetricsExporter: Cant read spooled batch: {:?}", err);
+                    self.report_error(&GCPMetricsExportError::spool(&err));
+                    return true;
+                }
+            };
+            let time_series_count = req.time_series.len();
+            let metric_types = time_series_metric_types(&req.time_series);
+            self.self_metrics.add_retry();
+            match self.send_time_series(req).await {
+                Ok(_) => {
+                    self.self_metrics.add_sent(time_series_count);
+                }
+                Err(err) if spool::is_retryable(&err) => {
+                    self.self_metrics.add_failed(time_series_count);
+                    self.report_error(&GCPMetricsExportError::from_rpc(
+                        ExportOperation::CreateTimeSeries,
+                        &err,
+                        metric_types,
+                        time_series_count,
+                    ));
+                    tracing::debug!("GCPMetricsExporter: Cant replay spooled time series: {:?}", err);
+                    return true;
+                }
+                Err(err) => {
+                    self.self_metrics.add_failed(time_series_count);
+                    self.report_error(&GCPMetricsExportError::from_rpc(
+                        ExportOperation::CreateTimeSeries,
+                        &err,
+                        metric_types,
+                        time_series_count,
+                    ));
+                    self.self_metrics.add_dropped(time_series_count);
+                    tracing::warn!("GCPMetricsExporter: Dropping spooled time series: {:?}", err);
+                }
+            }
+            self.remove_spooled(spool, &entry).await;
+        }
+        false
+    }
+
+    #[cfg(feature = "spool")]
+    async fn spool_request(&self, req: &google_cloud_monitoring_v3::model::CreateTimeSeriesRequest) {
+        if let Some(spool) = &self.spool {
+            if let Err(err) = spool.push(req).await {
+                tracing::warn!("GCPMetricsExporter: Cant spool time series: {:?}", err);
+                self.report_error(&GCPMetricsExportError::spool(&err));
+            }
+        }
+    }
+
+    #[cfg(feature = "spool")]
+    async fn update_queue_depth(&self) {
+        if let Some(spool) = &self.spool {
+            match spool.pending().await {
+                Ok(pending) => self.self_metrics.set_queue_depth(pending.len()),
+                Err(err) => self.report_error(&GCPMetricsExportError::spool(&err)),
+            }
+        }
+    }
+
+    #[cfg(feature = "spool")]
+    async fn remove_spooled(&self, spool: &spool::Spool, entry: &spool::SpoolEntry) {
+        if let Err(err) = spool.remove(entry).await {
+            tracing::warn!("GCPMetricsExporter: Cant remove spooled batch: {:?}", err);
+            self.report_error(&GCPMetricsExportError::spool(&err));
+        }
+    }
+
+    async fn export_metrics(&self, metrics: &ResourceMetrics) -> Result<(), OTelSdkError> {
+        let sys_time = SystemTime::now();
+        let started = self.self_metrics.start_export();
+        let resp = self
+            .exec_export(metrics)
+            .instrument(tracing::info_span!("gcp_metrics_exporter.export"))
+            .await
+            .map_err(OTelSdkError::from);
+        #[cfg(feature = "spool")]
+        self.update_queue_depth().await;
+        let new_sys_time = SystemTime::now();
+        let difference = new_sys_time.duration_since(sys_time).unwrap_or_default();
+        tracing::debug!("GCPMetricsExporter: export time: {:?}", difference);
+        self.self_metrics.record_export(started, difference, resp.is_ok());
+        resp
+    }
+}
+
+fn time_series_metric_types(time_series: &[google_cloud_monitoring_v3::model::TimeSeries]) -> Vec<String> {
+    time_series
+        .iter()
+        .filter_map(|time_series| time_series.metric.as_ref())
+        .map(|metric| metric.r#type.clone())
+        .unique()
+        .collect()
 }
 
 impl PushMetricsExporter for GCPMetricsExporter {
-    fn export(&self, metrics: &ResourceMetrics) -> impl std::future::Future<Output = Result<(), OTelSdkError>> + Send {
-        async {
-            let sys_time = SystemTime::now();
-            let resp = self.exec_export(metrics).await;
-            let new_sys_time = SystemTime::now();
-            let _difference = new_sys_time
-                .duration_since(sys_time)
-                .expect("Clock may have gone backwards")
-                .as_millis();
-            // info!("export time: {}", difference);
-            resp
+    /// Runs on the caller's executor, tokio or not (e.g. the SDK's thread
+    /// based `PeriodicReader` drives it with `futures_executor::block_on`).
+    /// Outside of a tokio runtime the export enters the runtime the exporter
+    /// was built on each time it is polled, so the HTTP client finds its
+    /// reactor and timers there; that runtime has to keep running (a
+    /// current_thread one must be driven by its own thread) for the request
+    /// to make progress.
+    async fn export(&self, metrics: &ResourceMetrics) -> Result<(), OTelSdkError> {
+        #[cfg(feature = "tokio")]
+        if let (Some(runtime), Err(_)) = (&self.runtime, tokio::runtime::Handle::try_current()) {
+            let mut export = std::pin::pin!(self.export_metrics(metrics));
+            return std::future::poll_fn(|cx| {
+                let _guard = runtime.enter();
+                export.as_mut().poll(cx)
+            })
+            .await;
         }
+        self.export_metrics(metrics).await
     }
 
     fn force_flush(&self) -> Result<(), OTelSdkError> {
@@ -551,7 +1216,7 @@ impl PushMetricsExporter for GCPMetricsExporter {
     }
 
     fn temporality(&self) -> Temporality {
-        Temporality::default()
+        self.temporality
     }
 
     fn shutdown_with_timeout(&self, _timeout: Duration) -> opentelemetry_sdk::error::OTelSdkResult {
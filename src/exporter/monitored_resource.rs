@@ -0,0 +1,573 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use google_cloud_api::model::{MetricDescriptor, MonitoredResource};
+use google_cloud_monitoring_v3::model::TimeSeries;
+use opentelemetry_sdk::Resource;
+
+use super::{
+    GCPMetricsExporterConfig, GCPMetricsExporterInitError, MonitoredResourceDataConfig, utils::normalize_label_key,
+};
+
+/// Labels of common monitored resource types, `project_id` is left out as
+/// Cloud Monitoring fills it from the request.
+/// https://cloud.google.com/monitoring/api/resources
+const KNOWN_MONITORED_RESOURCE_TYPES: &[(&str, &[&str])] = &[
+    ("global", &[]),
+    ("generic_task", &["location", "namespace", "job", "task_id"]),
+    ("generic_node", &["location", "namespace", "node_id"]),
+    ("gce_instance", &["instance_id", "zone"]),
+    ("k8s_cluster", &["location", "cluster_name"]),
+    ("k8s_node", &["location", "cluster_name", "node_name"]),
+    ("k8s_pod", &["location", "cluster_name", "namespace_name", "pod_name"]),
+    (
+        "k8s_container",
+        &[
+            "location",
+            "cluster_name",
+            "namespace_name",
+            "pod_name",
+            "container_name",
+        ],
+    ),
+    ("aws_ec2_instance", &["instance_id", "region", "aws_account"]),
+    (
+        "cloud_run_revision",
+        &["location", "service_name", "revision_name", "configuration_name"],
+    ),
+    ("cloud_function", &["region", "function_name"]),
+    ("gae_instance", &["location", "module_id", "version_id", "instance_id"]),
+    ("cloudsql_database", &["region", "database_id"]),
+    ("gcs_bucket", &["location", "bucket_name"]),
+    (
+        "prometheus_target",
+        &["location", "cluster", "namespace", "job", "instance"],
+    ),
+];
+
+/// Maps the OpenTelemetry [`Resource`] of an export to the monitored
+/// resource of its time series, `None` to send them without one.
+///
+/// It is not used when `custom_monitored_resource_data` is set.
+pub trait MonitoredResourceMapper: fmt::Debug + Send + Sync {
+    fn map(&self, resource: &Resource) -> Option<MonitoredResourceDataConfig>;
+}
+
+/// Default [`MonitoredResourceMapper`], the GCP resource detector mapping
+/// (`gce_instance`, `k8s_container`, `aws_ec2_instance`, ... falling back to
+/// `generic_task` or `generic_node`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GcpResourceDetectorMapper;
+
+impl MonitoredResourceMapper for GcpResourceDetectorMapper {
+    fn map(&self, resource: &Resource) -> Option<MonitoredResourceDataConfig> {
+        opentelemetry_resourcedetector_gcp_rust::mapping::get_monitored_resource(resource).map(|data| {
+            MonitoredResourceDataConfig {
+                r#type: data.r#type,
+                labels: data.labels,
+            }
+        })
+    }
+}
+
+/// Always maps to `generic_task`, labels from `service.*` and `cloud.*`:
+/// - location: `cloud.availability_zone`, `cloud.region` or `global`
+/// - namespace: `service.namespace`
+/// - job: `service.name` or `faas.name`
+/// - task_id: `service.instance.id`, `faas.instance` or `host.name`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GenericTaskMapper;
+
+impl MonitoredResourceMapper for GenericTaskMapper {
+    fn map(&self, resource: &Resource) -> Option<MonitoredResourceDataConfig> {
+        Some(MonitoredResourceDataConfig {
+            r#type: "generic_task".to_string(),
+            labels: HashMap::from([
+                ("location".to_string(), location(resource)),
+                (
+                    "namespace".to_string(),
+                    first_attribute(resource, &["service.namespace"]).unwrap_or_default(),
+                ),
+                (
+                    "job".to_string(),
+                    first_attribute(resource, &["service.name", "faas.name"]).unwrap_or_default(),
+                ),
+                (
+                    "task_id".to_string(),
+                    first_attribute(resource, &["service.instance.id", "faas.instance", "host.name"])
+                        .unwrap_or_default(),
+                ),
+            ]),
+        })
+    }
+}
+
+/// Always maps to `generic_node`, labels from `host.*` and `cloud.*`:
+/// - location: `cloud.availability_zone`, `cloud.region` or `global`
+/// - namespace: `service.namespace`
+/// - node_id: `host.id` or `host.name`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GenericNodeMapper;
+
+impl MonitoredResourceMapper for GenericNodeMapper {
+    fn map(&self, resource: &Resource) -> Option<MonitoredResourceDataConfig> {
+        Some(MonitoredResourceDataConfig {
+            r#type: "generic_node".to_string(),
+            labels: HashMap::from([
+                ("location".to_string(), location(resource)),
+                (
+                    "namespace".to_string(),
+                    first_attribute(resource, &["service.namespace"]).unwrap_or_default(),
+                ),
+                (
+                    "node_id".to_string(),
+                    first_attribute(resource, &["host.id", "host.name"]).unwrap_or_default(),
+                ),
+            ]),
+        })
+    }
+}
+
+/// First non-empty value of `keys` in `resource`.
+pub(crate) fn first_attribute(resource: &Resource, keys: &[&'static str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| resource.get(&opentelemetry::Key::from_static_str(key)))
+        .map(|value| value.to_string())
+        .find(|value| !value.is_empty())
+}
+
+pub(crate) fn location(resource: &Resource) -> String {
+    first_attribute(resource, &["cloud.availability_zone", "cloud.region"]).unwrap_or_else(|| "global".to_string())
+}
+
+/// Custom [`MonitoredResourceResolver`], called with the metric name and the
+/// metric labels of a time series. Labels consumed by the returned resource
+/// should be removed from `labels`.
+pub type MonitoredResourceResolverFn =
+    Arc<dyn Fn(&str, &mut HashMap<String, String>) -> Option<MonitoredResourceDataConfig> + Send + Sync>;
+
+/// Replaces the monitored resource of some time series, e.g. for metrics
+/// describing a Cloud SQL instance or a bucket instead of this process.
+///
+/// The first resolver returning a resource wins, the other time series keep
+/// the detected or `custom_monitored_resource_data` resource.
+#[derive(Clone)]
+pub enum MonitoredResourceResolver {
+    /// Every time series of the metric named `metric_name` uses `resource`.
+    Metric {
+        metric_name: String,
+        resource: MonitoredResourceDataConfig,
+    },
+    /// `resource` with the labels in `attribute_labels` (resource label ->
+    /// data point attribute) taken from the data point attributes. Applies to
+    /// `metric_names`, or to every metric if empty, when all the attributes
+    /// are set. The attributes are removed from the metric labels.
+    Attributes {
+        metric_names: Vec<String>,
+        resource: MonitoredResourceDataConfig,
+        attribute_labels: HashMap<String, String>,
+    },
+    Custom(MonitoredResourceResolverFn),
+}
+
+impl fmt::Debug for MonitoredResourceResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Metric { metric_name, resource } => f
+                .debug_struct("Metric")
+                .field("metric_name", metric_name)
+                .field("resource", resource)
+                .finish(),
+            Self::Attributes {
+                metric_names,
+                resource,
+                attribute_labels,
+            } => f
+                .debug_struct("Attributes")
+                .field("metric_names", metric_names)
+                .field("resource", resource)
+                .field("attribute_labels", attribute_labels)
+                .finish(),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// Custom resolvers are equal when they are clones of the same function.
+impl PartialEq for MonitoredResourceResolver {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Metric { metric_name, resource },
+                Self::Metric {
+                    metric_name: other_metric_name,
+                    resource: other_resource,
+                },
+            ) => metric_name == other_metric_name && resource == other_resource,
+            (
+                Self::Attributes {
+                    metric_names,
+                    resource,
+                    attribute_labels,
+                },
+                Self::Attributes {
+                    metric_names: other_metric_names,
+                    resource: other_resource,
+                    attribute_labels: other_attribute_labels,
+                },
+            ) => {
+                metric_names == other_metric_names
+                    && resource == other_resource
+                    && attribute_labels == other_attribute_labels
+            }
+            (Self::Custom(resolver), Self::Custom(other_resolver)) => Arc::ptr_eq(resolver, other_resolver),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for MonitoredResourceResolver {}
+
+impl MonitoredResourceResolver {
+    fn resolve(&self, metric_name: &str, labels: &mut HashMap<String, String>) -> Option<MonitoredResourceDataConfig> {
+        match self {
+            Self::Metric {
+                metric_name: name,
+                resource,
+            } => (name == metric_name).then(|| resource.clone()),
+            Self::Attributes {
+                metric_names,
+                resource,
+                attribute_labels,
+            } => {
+                if !metric_names.is_empty() && !metric_names.iter().any(|name| name == metric_name) {
+                    return None;
+                }
+                let keys = attribute_labels
+                    .values()
+                    .map(|attribute| normalize_label_key(attribute))
+                    .collect::<Vec<_>>();
+                if !keys.iter().all(|key| labels.contains_key(key)) {
+                    return None;
+                }
+                let mut resource = resource.clone();
+                for (resource_label, attribute) in attribute_labels {
+                    let value = labels.remove(&normalize_label_key(attribute)).unwrap_or_default();
+                    resource.labels.insert(resource_label.clone(), value);
+                }
+                Some(resource)
+            }
+            Self::Custom(resolver) => resolver(metric_name, labels),
+        }
+    }
+}
+
+/// Monitored resources of `config` known before the first export, with
+/// the label keys they will have.
+fn configured_resources(config: &GCPMetricsExporterConfig) -> Vec<(&str, Vec<&str>)> {
+    let mut resources = Vec::new();
+    if let Some(data) = &config.custom_monitored_resource_data {
+        resources.push((data.r#type.as_str(), data.labels.keys().map(String::as_str).collect()));
+    }
+    for resolver in &config.monitored_resource_resolvers {
+        match resolver {
+            MonitoredResourceResolver::Metric { resource, .. } => resources.push((
+                resource.r#type.as_str(),
+                resource.labels.keys().map(String::as_str).collect(),
+            )),
+            MonitoredResourceResolver::Attributes {
+                resource,
+                attribute_labels,
+                ..
+            } => resources.push((
+                resource.r#type.as_str(),
+                resource
+                    .labels
+                    .keys()
+                    .chain(attribute_labels.keys())
+                    .map(String::as_str)
+                    .collect(),
+            )),
+            MonitoredResourceResolver::Custom(_) => {}
+        }
+    }
+    resources
+}
+
+/// Compares the labels of a `r#type` resource with `expected`, the labels
+/// of the resource type.
+fn check_labels(r#type: &str, labels: &[&str], expected: &[&str]) -> Result<(), String> {
+    let missing = expected
+        .iter()
+        .filter(|label| !labels.contains(label))
+        .copied()
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(format!(
+            "monitored resource {:?} is missing the labels {:?}",
+            r#type, missing
+        ));
+    }
+    let unknown = labels
+        .iter()
+        .filter(|label| **label != "project_id" && !expected.contains(label))
+        .copied()
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err(format!(
+            "monitored resource {:?} has unknown labels {:?}, expected {:?}",
+            r#type, unknown, expected
+        ));
+    }
+    Ok(())
+}
+
+/// Checks the configured monitored resources of a known type have exactly
+/// its labels, see [`KNOWN_MONITORED_RESOURCE_TYPES`]. Other types are only
+/// checked by [`check_monitored_resource_descriptors`].
+pub(crate) fn validate_monitored_resources(config: &GCPMetricsExporterConfig) -> Result<(), String> {
+    for (r#type, labels) in configured_resources(config) {
+        if r#type.is_empty() {
+            return Err("monitored resource type must not be empty".to_string());
+        }
+        let Some((_, expected)) = KNOWN_MONITORED_RESOURCE_TYPES
+            .iter()
+            .find(|(known_type, _)| *known_type == r#type)
+        else {
+            continue;
+        };
+        check_labels(r#type, &labels, expected)?;
+    }
+    Ok(())
+}
+
+/// Compares the configured monitored resources with the monitored resource
+/// descriptors of `project_id`.
+pub(crate) async fn check_monitored_resource_descriptors(
+    client: &google_cloud_monitoring_v3::client::MetricService,
+    project_id: &str,
+    config: &GCPMetricsExporterConfig,
+) -> Result<(), GCPMetricsExporterInitError> {
+    for (r#type, labels) in configured_resources(config) {
+        let req = google_cloud_monitoring_v3::model::GetMonitoredResourceDescriptorRequest::new().set_name(format!(
+            "projects/{}/monitoredResourceDescriptors/{}",
+            project_id, r#type
+        ));
+        let descriptor = match client
+            .get_monitored_resource_descriptor()
+            .with_request(req)
+            .send()
+            .await
+        {
+            Ok(descriptor) => descriptor,
+            Err(err)
+                if err.status().map(|status| status.code) == Some(google_cloud_gax::error::rpc::Code::NotFound) =>
+            {
+                return Err(GCPMetricsExporterInitError::InvalidConfig(format!(
+                    "unknown monitored resource type {:?}",
+                    r#type
+                )));
+            }
+            Err(err) => return Err(GCPMetricsExporterInitError::MonitoredResourceDescriptor(err)),
+        };
+        let expected = descriptor
+            .labels
+            .iter()
+            .map(|label| label.key.as_str())
+            .filter(|key| *key != "project_id")
+            .collect::<Vec<_>>();
+        check_labels(r#type, &labels, &expected).map_err(GCPMetricsExporterInitError::InvalidConfig)?;
+    }
+    Ok(())
+}
+
+/// Applies `resolvers` to the time series of the metric `metric_name`. Metric
+/// labels consumed by a resolver are also removed from `descriptor`.
+pub(crate) fn resolve_monitored_resources(
+    resolvers: &[MonitoredResourceResolver],
+    metric_name: &str,
+    descriptor: &mut MetricDescriptor,
+    all_series: &mut [TimeSeries],
+) {
+    let mut consumed_labels = false;
+    for time_series in all_series.iter_mut() {
+        let Some(metric) = time_series.metric.as_mut() else {
+            continue;
+        };
+        let labels_count = metric.labels.len();
+        let Some(resource) = resolvers
+            .iter()
+            .find_map(|resolver| resolver.resolve(metric_name, &mut metric.labels))
+        else {
+            continue;
+        };
+        consumed_labels |= metric.labels.len() != labels_count;
+        time_series.resource = Some(
+            MonitoredResource::new()
+                .set_type(resource.r#type)
+                .set_labels(resource.labels),
+        );
+    }
+    if consumed_labels {
+        descriptor.labels.retain(|label| {
+            all_series.iter().any(|time_series| {
+                time_series
+                    .metric
+                    .as_ref()
+                    .is_some_and(|metric| metric.labels.contains_key(&label.key))
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use google_cloud_api::model::{LabelDescriptor, Metric};
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    fn time_series(labels: &[(&str, &str)]) -> TimeSeries {
+        TimeSeries::new()
+            .set_metric(
+                Metric::new().set_labels(
+                    labels
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect::<HashMap<_, _>>(),
+                ),
+            )
+            .set_resource(MonitoredResource::new().set_type("generic_node"))
+    }
+
+    #[test]
+    fn test_validate_monitored_resources() {
+        let config = |r#type: &str, labels: &[&str]| GCPMetricsExporterConfig {
+            custom_monitored_resource_data: Some(MonitoredResourceDataConfig {
+                r#type: r#type.to_string(),
+                labels: labels
+                    .iter()
+                    .map(|key| (key.to_string(), "value".to_string()))
+                    .collect(),
+            }),
+            ..Default::default()
+        };
+        assert!(validate_monitored_resources(&config("global", &["project_id"])).is_ok());
+        assert!(
+            validate_monitored_resources(&config("generic_task", &["location", "namespace", "job", "task_id"])).is_ok()
+        );
+        assert!(validate_monitored_resources(&config("my_own_type", &["anything"])).is_ok());
+        assert_eq!(
+            validate_monitored_resources(&config("generic_task", &["location", "namespace", "job"])),
+            Err(r#"monitored resource "generic_task" is missing the labels ["task_id"]"#.to_string())
+        );
+        assert!(validate_monitored_resources(&config("global", &["zone"])).is_err());
+
+        let resolver_config = GCPMetricsExporterConfig {
+            monitored_resource_resolvers: vec![MonitoredResourceResolver::Attributes {
+                metric_names: vec![],
+                resource: MonitoredResourceDataConfig {
+                    r#type: "gcs_bucket".to_string(),
+                    labels: HashMap::from([("location".to_string(), "eu".to_string())]),
+                },
+                attribute_labels: HashMap::from([("bucket_name".to_string(), "bucket".to_string())]),
+            }],
+            ..Default::default()
+        };
+        assert!(validate_monitored_resources(&resolver_config).is_ok());
+    }
+
+    #[test]
+    fn test_generic_mappers() {
+        let resource = Resource::builder_empty()
+            .with_attributes(vec![
+                opentelemetry::KeyValue::new("service.name", "billing"),
+                opentelemetry::KeyValue::new("service.namespace", "payments"),
+                opentelemetry::KeyValue::new("service.instance.id", "billing-1"),
+                opentelemetry::KeyValue::new("host.name", "rack-7"),
+                opentelemetry::KeyValue::new("cloud.region", "us-east-1"),
+            ])
+            .build();
+        assert_eq!(
+            GenericTaskMapper.map(&resource),
+            Some(MonitoredResourceDataConfig {
+                r#type: "generic_task".to_string(),
+                labels: HashMap::from([
+                    ("location".to_string(), "us-east-1".to_string()),
+                    ("namespace".to_string(), "payments".to_string()),
+                    ("job".to_string(), "billing".to_string()),
+                    ("task_id".to_string(), "billing-1".to_string()),
+                ]),
+            })
+        );
+        assert_eq!(
+            GenericNodeMapper.map(&Resource::builder_empty().build()),
+            Some(MonitoredResourceDataConfig {
+                r#type: "generic_node".to_string(),
+                labels: HashMap::from([
+                    ("location".to_string(), "global".to_string()),
+                    ("namespace".to_string(), "".to_string()),
+                    ("node_id".to_string(), "".to_string()),
+                ]),
+            })
+        );
+        let node = GenericNodeMapper.map(&resource).unwrap();
+        assert_eq!(node.labels.get("node_id"), Some(&"rack-7".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_monitored_resources() {
+        let resolvers = vec![
+            MonitoredResourceResolver::Attributes {
+                metric_names: vec!["db.connections".to_string()],
+                resource: MonitoredResourceDataConfig {
+                    r#type: "cloudsql_database".to_string(),
+                    labels: HashMap::from([("project_id".to_string(), "my-project".to_string())]),
+                },
+                attribute_labels: HashMap::from([("database_id".to_string(), "db.instance".to_string())]),
+            },
+            MonitoredResourceResolver::Metric {
+                metric_name: "db.connections".to_string(),
+                resource: MonitoredResourceDataConfig {
+                    r#type: "global".to_string(),
+                    labels: HashMap::new(),
+                },
+            },
+        ];
+        let mut descriptor = MetricDescriptor::new().set_labels(vec![
+            LabelDescriptor::new().set_key("db_instance"),
+            LabelDescriptor::new().set_key("state"),
+        ]);
+        let mut all_series = vec![
+            time_series(&[("db_instance", "my-project:main"), ("state", "idle")]),
+            time_series(&[("state", "idle")]),
+        ];
+        resolve_monitored_resources(&resolvers, "db.connections", &mut descriptor, &mut all_series);
+
+        let resource = all_series[0].resource.clone().unwrap();
+        assert_eq!(resource.r#type, "cloudsql_database");
+        assert_eq!(
+            resource.labels,
+            HashMap::from([
+                ("project_id".to_string(), "my-project".to_string()),
+                ("database_id".to_string(), "my-project:main".to_string()),
+            ])
+        );
+        assert_eq!(
+            all_series[0].metric.clone().unwrap().labels,
+            HashMap::from([("state".to_string(), "idle".to_string())])
+        );
+        assert_eq!(all_series[1].resource.clone().unwrap().r#type, "global");
+        assert_eq!(
+            descriptor
+                .labels
+                .iter()
+                .map(|label| label.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["state"]
+        );
+
+        // other metrics keep their resource
+        let mut all_series = vec![time_series(&[("db_instance", "my-project:main")])];
+        resolve_monitored_resources(&resolvers, "other", &mut descriptor, &mut all_series);
+        assert_eq!(all_series[0].resource.clone().unwrap().r#type, "generic_node");
+    }
+}
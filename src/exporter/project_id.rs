@@ -0,0 +1,384 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use opentelemetry_sdk::Resource;
+
+use super::{
+    GCPMetricsExporterConfig, GCPMetricsExporterInitError, client::credentials_project_id, dry_run::DRY_RUN_PROJECT_ID,
+};
+
+const DEFAULT_METADATA_URL: &str = "http://metadata.google.internal";
+const PROJECT_ID_ENV_VARS: [&str; 3] = ["GOOGLE_CLOUD_PROJECT", "GCLOUD_PROJECT", "CLOUDSDK_CORE_PROJECT"];
+
+/// Where the project id of an exporter comes from, see
+/// [`crate::GCPMetricsExporter::project_id_source`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectIdSource {
+    /// `project_id` of the config, or given to the exporter directly.
+    Config,
+    /// `project_id` label of `custom_monitored_resource_data`.
+    MonitoredResourceLabel,
+    /// Placeholder project id of dry-run mode.
+    DryRun,
+    /// `GOOGLE_CLOUD_PROJECT`, `GCLOUD_PROJECT` or `CLOUDSDK_CORE_PROJECT`.
+    EnvVar(&'static str),
+    /// `project_id` of the service account key or of the
+    /// `GOOGLE_APPLICATION_CREDENTIALS` file.
+    CredentialsFile,
+    /// `core/project` of the active gcloud configuration.
+    GcloudConfig,
+    MetadataServer,
+    /// `cloud.account.id` attribute of [`ProjectIdDetectionConfig::resource`].
+    ResourceAttribute,
+}
+
+impl fmt::Display for ProjectIdSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectIdSource::Config => f.write_str("config"),
+            ProjectIdSource::MonitoredResourceLabel => f.write_str("custom monitored resource label"),
+            ProjectIdSource::DryRun => f.write_str("dry run"),
+            ProjectIdSource::EnvVar(name) => write!(f, "env var {}", name),
+            ProjectIdSource::CredentialsFile => f.write_str("credentials file"),
+            ProjectIdSource::GcloudConfig => f.write_str("gcloud config"),
+            ProjectIdSource::MetadataServer => f.write_str("metadata server"),
+            ProjectIdSource::ResourceAttribute => f.write_str("resource attribute cloud.account.id"),
+        }
+    }
+}
+
+/// How the project id is detected when the config does not set it.
+///
+/// The sources are tried in the order of [`ProjectIdSource`]: config,
+/// custom monitored resource label, env vars, credentials file, gcloud
+/// config, metadata server, resource attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectIdDetectionConfig {
+    /// metadata_url: Base URL of the metadata server, `http://$GCE_METADATA_HOST`
+    ///     or `http://metadata.google.internal` by default.
+    pub metadata_url: Option<String>,
+    /// metadata_timeout: Timeout of one metadata server request, 1 second by default.
+    pub metadata_timeout: Duration,
+    /// metadata_attempts: Metadata server requests before giving up, 3 by default.
+    ///     0 skips the metadata server.
+    pub metadata_attempts: u32,
+    /// resource: Resource whose `cloud.account.id` attribute is the last resort,
+    ///     e.g. the one detected by `GoogleCloudResourceDetector`.
+    pub resource: Option<Resource>,
+}
+
+impl Default for ProjectIdDetectionConfig {
+    fn default() -> Self {
+        Self {
+            metadata_url: None,
+            metadata_timeout: Duration::from_secs(1),
+            metadata_attempts: 3,
+            resource: None,
+        }
+    }
+}
+
+/// Successful metadata server lookups by base URL, they do not change
+/// during the lifetime of the process.
+fn metadata_cache() -> &'static Mutex<HashMap<String, String>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub(crate) async fn resolve_project_id(
+    config: &GCPMetricsExporterConfig,
+    service_account_key: Option<&serde_json::Value>,
+) -> Result<(String, ProjectIdSource), GCPMetricsExporterInitError> {
+    resolve_project_id_with_env(config, service_account_key, &|name| std::env::var(name).ok()).await
+}
+
+/// [`resolve_project_id`] reading env vars with `env`.
+pub(crate) async fn resolve_project_id_with_env(
+    config: &GCPMetricsExporterConfig,
+    service_account_key: Option<&serde_json::Value>,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<(String, ProjectIdSource), GCPMetricsExporterInitError> {
+    if let Some(project_id) = non_empty(config.project_id.clone()) {
+        return Ok((project_id, ProjectIdSource::Config));
+    }
+    let resource_label = config
+        .custom_monitored_resource_data
+        .as_ref()
+        .and_then(|v| v.labels.get("project_id").cloned());
+    if let Some(project_id) = non_empty(resource_label) {
+        return Ok((project_id, ProjectIdSource::MonitoredResourceLabel));
+    }
+    if config.dry_run.is_some() {
+        return Ok((DRY_RUN_PROJECT_ID.to_string(), ProjectIdSource::DryRun));
+    }
+    for name in PROJECT_ID_ENV_VARS {
+        if let Some(project_id) = non_empty(env(name)) {
+            return Ok((project_id, ProjectIdSource::EnvVar(name)));
+        }
+    }
+    if let Some(project_id) = credentials_project_id(&config.credentials, service_account_key, env) {
+        return Ok((project_id, ProjectIdSource::CredentialsFile));
+    }
+    if let Some(project_id) = gcloud_config_project_id(env) {
+        return Ok((project_id, ProjectIdSource::GcloudConfig));
+    }
+    let detection = &config.project_id_detection;
+    let metadata_err = match metadata_project_id(detection, env).await {
+        Ok(project_id) => return Ok((project_id, ProjectIdSource::MetadataServer)),
+        Err(err) => err,
+    };
+    let resource_attribute = detection.resource.as_ref().and_then(|resource| {
+        resource
+            .get(&opentelemetry::Key::from_static_str("cloud.account.id"))
+            .map(|value| value.to_string())
+    });
+    if let Some(project_id) = non_empty(resource_attribute) {
+        return Ok((project_id, ProjectIdSource::ResourceAttribute));
+    }
+    Err(GCPMetricsExporterInitError::ProjectIdDedection(std::io::Error::other(
+        format!(
+            "no project id in config, {}, credentials file or gcloud config, metadata server: {}",
+            PROJECT_ID_ENV_VARS.join(", "),
+            metadata_err
+        ),
+    )))
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// `project` in the `[core]` section of the active gcloud configuration.
+fn gcloud_config_project_id(env: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    let config_dir = match env("CLOUDSDK_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(env("APPDATA")?).join("gcloud"),
+        None => PathBuf::from(env("HOME")?).join(".config").join("gcloud"),
+    };
+    let active = env("CLOUDSDK_ACTIVE_CONFIG_NAME")
+        .or_else(|| std::fs::read_to_string(config_dir.join("active_config")).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "default".to_string());
+    let content = std::fs::read_to_string(config_dir.join("configurations").join(format!("config_{}", active))).ok()?;
+    let mut in_core = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_core = line == "[core]";
+        } else if let Some((key, value)) = line.split_once('=') {
+            if in_core && key.trim() == "project" {
+                return non_empty(Some(value.to_string()));
+            }
+        }
+    }
+    None
+}
+
+async fn metadata_project_id(
+    detection: &ProjectIdDetectionConfig,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    if detection.metadata_attempts == 0 {
+        return Err("disabled".to_string());
+    }
+    let base_url = detection
+        .metadata_url
+        .clone()
+        .or_else(|| env("GCE_METADATA_HOST").map(|host| format!("http://{}", host)))
+        .unwrap_or_else(|| DEFAULT_METADATA_URL.to_string());
+    let base_url = base_url.trim_end_matches('/').to_string();
+    if let Some(project_id) = metadata_cache()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&base_url)
+    {
+        return Ok(project_id.clone());
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(detection.metadata_timeout)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let url = format!("{}/computeMetadata/v1/project/project-id", base_url);
+    let mut last_err = String::new();
+    for attempt in 1..=detection.metadata_attempts {
+        match client.get(&url).header("Metadata-Flavor", "Google").send().await {
+            Ok(res) if res.status().is_success() => {
+                let project_id = res.text().await.map_err(|e| e.to_string())?;
+                let project_id = non_empty(Some(project_id)).ok_or("empty project id")?;
+                metadata_cache()
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(base_url, project_id.clone());
+                return Ok(project_id);
+            }
+            // the server answered, asking again will not help
+            Ok(res) => return Err(format!("metadata server returned error: {}", res.status())),
+            Err(err) => {
+                tracing::debug!(
+                    "GCPMetricsExporter: metadata server attempt {}/{} failed: {:?}",
+                    attempt,
+                    detection.metadata_attempts,
+                    err
+                );
+                last_err = format!("error querying metadata server: {}", err);
+            }
+        }
+    }
+    Err(last_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MonitoredResourceDataConfig;
+    use opentelemetry::KeyValue;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn env_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> + use<> {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn no_metadata_config() -> GCPMetricsExporterConfig {
+        GCPMetricsExporterConfig {
+            project_id_detection: ProjectIdDetectionConfig {
+                metadata_attempts: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Answers every request on a local port with `project_id` if it has the
+    /// `Metadata-Flavor: Google` header.
+    async fn start_metadata_stub(project_id: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let response = if request.starts_with("get /computemetadata/v1/project/project-id")
+                    && request.contains("metadata-flavor: google")
+                {
+                    format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        project_id.len(),
+                        project_id
+                    )
+                } else {
+                    "HTTP/1.1 403 Forbidden\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_string()
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_project_id_chain_order() {
+        let mut config = no_metadata_config();
+        config.project_id_detection.resource = Some(
+            Resource::builder_empty()
+                .with_attributes(vec![KeyValue::new("cloud.account.id", "resource-project")])
+                .build(),
+        );
+        let env = env_from(&[]);
+        assert_eq!(
+            resolve_project_id_with_env(&config, None, &env).await.unwrap(),
+            ("resource-project".to_string(), ProjectIdSource::ResourceAttribute)
+        );
+
+        let env = env_from(&[
+            ("GCLOUD_PROJECT", "gcloud-env"),
+            ("CLOUDSDK_CORE_PROJECT", "cloudsdk-env"),
+        ]);
+        assert_eq!(
+            resolve_project_id_with_env(&config, None, &env).await.unwrap(),
+            ("gcloud-env".to_string(), ProjectIdSource::EnvVar("GCLOUD_PROJECT"))
+        );
+
+        config.custom_monitored_resource_data = Some(MonitoredResourceDataConfig {
+            r#type: "global".to_string(),
+            labels: HashMap::from([("project_id".to_string(), "label-project".to_string())]),
+        });
+        assert_eq!(
+            resolve_project_id_with_env(&config, None, &env).await.unwrap(),
+            ("label-project".to_string(), ProjectIdSource::MonitoredResourceLabel)
+        );
+
+        config.project_id = Some("config-project".to_string());
+        assert_eq!(
+            resolve_project_id_with_env(&config, None, &env).await.unwrap(),
+            ("config-project".to_string(), ProjectIdSource::Config)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gcloud_config_project_id() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::create_dir_all(dir.join("configurations")).unwrap();
+        std::fs::write(dir.join("active_config"), "work\n").unwrap();
+        std::fs::write(
+            dir.join("configurations").join("config_work"),
+            "[auth]\nproject = not-this-one\n[core]\naccount = me@example.com\nproject = gcloud-project\n",
+        )
+        .unwrap();
+        let env = env_from(&[("CLOUDSDK_CONFIG", dir.to_str().unwrap())]);
+        assert_eq!(
+            resolve_project_id_with_env(&no_metadata_config(), None, &env)
+                .await
+                .unwrap(),
+            ("gcloud-project".to_string(), ProjectIdSource::GcloudConfig)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metadata_server_project_id() {
+        let url = start_metadata_stub("metadata-project").await;
+        let config = GCPMetricsExporterConfig {
+            project_id_detection: ProjectIdDetectionConfig {
+                metadata_url: Some(url),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let env = env_from(&[]);
+        assert_eq!(
+            resolve_project_id_with_env(&config, None, &env).await.unwrap(),
+            ("metadata-project".to_string(), ProjectIdSource::MetadataServer)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metadata_server_unreachable() {
+        // nothing listens on the port once the listener is dropped
+        let url = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let config = GCPMetricsExporterConfig {
+            project_id_detection: ProjectIdDetectionConfig {
+                metadata_url: Some(url),
+                metadata_timeout: Duration::from_millis(200),
+                metadata_attempts: 2,
+                resource: None,
+            },
+            ..Default::default()
+        };
+        let env = env_from(&[]);
+        let err = resolve_project_id_with_env(&config, None, &env).await.unwrap_err();
+        assert!(matches!(err, GCPMetricsExporterInitError::ProjectIdDedection(_)));
+    }
+}
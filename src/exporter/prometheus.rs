@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use google_cloud_api::model::{
+    MetricDescriptor,
+    metric_descriptor::{MetricKind, ValueType},
+};
+use opentelemetry_sdk::Resource;
+
+use super::{
+    MonitoredResourceDataConfig,
+    monitored_resource::{MonitoredResourceMapper, first_attribute, location},
+};
+
+pub(crate) const PROMETHEUS_PREFIX: &str = "prometheus.googleapis.com";
+
+/// Writes metrics the way Google Cloud Managed Service for Prometheus does,
+/// so they can be queried with PromQL next to scraped metrics.
+///
+/// Metric types are `prometheus.googleapis.com/<name>/<counter|gauge|histogram>`
+/// with a Prometheus style name, e.g. `http.server.duration` in `s` becomes
+/// `prometheus.googleapis.com/http_server_duration_seconds/histogram`. Every
+/// time series uses the `prometheus_target` monitored resource, see
+/// [`PrometheusTargetMapper`], counters and gauges are written as doubles and
+/// no metric descriptors are created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrometheusConfig {
+    /// Appends the unit to the name, e.g. `_seconds` or `_bytes`. Default true.
+    pub add_unit_suffix: bool,
+}
+
+impl Default for PrometheusConfig {
+    fn default() -> Self {
+        Self { add_unit_suffix: true }
+    }
+}
+
+impl PrometheusConfig {
+    /// Rewrites the type and value type of `descriptor` built for the metric
+    /// `name` in `unit`. Only `monotonic` cumulative metrics are counters, an
+    /// UpDownCounter written as CUMULATIVE stays a gauge: its value goes down.
+    pub(crate) fn apply(&self, name: &str, unit: &str, monotonic: bool, descriptor: &mut MetricDescriptor) {
+        let (suffix, is_counter) = match (&descriptor.metric_kind, &descriptor.value_type, monotonic) {
+            (_, ValueType::Distribution, _) => ("histogram", false),
+            (MetricKind::Cumulative, _, true) => ("counter", true),
+            _ => ("gauge", false),
+        };
+        if descriptor.value_type == ValueType::Int64 {
+            descriptor.value_type = ValueType::Double;
+        }
+        let mut name = prometheus_name(name);
+        if is_counter && name.ends_with("_total") {
+            name.truncate(name.len() - "_total".len());
+        }
+        if self.add_unit_suffix {
+            if let Some(unit) = unit_suffix(unit, suffix == "gauge") {
+                if !name.ends_with(&format!("_{}", unit)) {
+                    name = format!("{}_{}", name, unit);
+                }
+            }
+        }
+        if is_counter && !name.ends_with("_total") {
+            name.push_str("_total");
+        }
+        descriptor.r#type = format!("{}/{}/{}", PROMETHEUS_PREFIX, name, suffix);
+    }
+}
+
+/// Replaces the characters Prometheus does not allow in metric names by `_`.
+fn prometheus_name(name: &str) -> String {
+    let mut sanitized = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if sanitized.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// Prometheus name of a UCUM `unit`, `{annotations}` are dropped and `1` is
+/// only a `ratio` for gauges.
+fn unit_suffix(unit: &str, is_gauge: bool) -> Option<String> {
+    let unit = match unit.find('{') {
+        Some(start) => &unit[..start],
+        None => unit,
+    };
+    if unit == "1" {
+        return is_gauge.then(|| "ratio".to_string());
+    }
+    let (main, per) = match unit.split_once('/') {
+        Some((main, per)) => (main, Some(per)),
+        None => (unit, None),
+    };
+    let main = unit_name(main);
+    let per = per.map(|per| match per {
+        "s" => "second".to_string(),
+        "m" => "minute".to_string(),
+        "h" => "hour".to_string(),
+        "d" => "day".to_string(),
+        "w" => "week".to_string(),
+        "mo" => "month".to_string(),
+        "y" => "year".to_string(),
+        _ => unit_name(per),
+    });
+    match (main.is_empty(), per) {
+        (true, None) => None,
+        (false, None) => Some(main),
+        (true, Some(per)) if per.is_empty() => None,
+        (true, Some(per)) => Some(format!("per_{}", per)),
+        (false, Some(per)) if per.is_empty() => Some(main),
+        (false, Some(per)) => Some(format!("{}_per_{}", main, per)),
+    }
+}
+
+fn unit_name(unit: &str) -> String {
+    let name = match unit {
+        "d" => "days",
+        "h" => "hours",
+        "min" => "minutes",
+        "s" => "seconds",
+        "ms" => "milliseconds",
+        "us" => "microseconds",
+        "ns" => "nanoseconds",
+        "By" => "bytes",
+        "KiBy" => "kibibytes",
+        "MiBy" => "mebibytes",
+        "GiBy" => "gibibytes",
+        "TiBy" => "tebibytes",
+        "KBy" => "kilobytes",
+        "MBy" => "megabytes",
+        "GBy" => "gigabytes",
+        "TBy" => "terabytes",
+        "m" => "meters",
+        "V" => "volts",
+        "A" => "amperes",
+        "J" => "joules",
+        "W" => "watts",
+        "g" => "grams",
+        "Cel" => "celsius",
+        "Hz" => "hertz",
+        "%" => "percent",
+        other => other,
+    };
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect()
+}
+
+/// Maps to the `prometheus_target` monitored resource of Managed Service for
+/// Prometheus:
+/// - location: `cloud.availability_zone`, `cloud.region` or `global`
+/// - cluster: `k8s.cluster.name`
+/// - namespace: `k8s.namespace.name` or `service.namespace`
+/// - job: `service.name`, prefixed by `service.namespace/` if set
+/// - instance: `service.instance.id`, `k8s.pod.name` or `host.name`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrometheusTargetMapper;
+
+impl MonitoredResourceMapper for PrometheusTargetMapper {
+    fn map(&self, resource: &Resource) -> Option<MonitoredResourceDataConfig> {
+        let service_namespace = first_attribute(resource, &["service.namespace"]);
+        let service_name = first_attribute(resource, &["service.name"]).unwrap_or_default();
+        let job = match &service_namespace {
+            Some(service_namespace) => format!("{}/{}", service_namespace, service_name),
+            None => service_name,
+        };
+        Some(MonitoredResourceDataConfig {
+            r#type: "prometheus_target".to_string(),
+            labels: HashMap::from([
+                ("location".to_string(), location(resource)),
+                (
+                    "cluster".to_string(),
+                    first_attribute(resource, &["k8s.cluster.name"]).unwrap_or_default(),
+                ),
+                (
+                    "namespace".to_string(),
+                    first_attribute(resource, &["k8s.namespace.name"])
+                        .or(service_namespace)
+                        .unwrap_or_default(),
+                ),
+                ("job".to_string(), job),
+                (
+                    "instance".to_string(),
+                    first_attribute(resource, &["service.instance.id", "k8s.pod.name", "host.name"])
+                        .unwrap_or_default(),
+                ),
+            ]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    fn metric_type(name: &str, unit: &str, kind: MetricKind, value_type: ValueType) -> (String, ValueType) {
+        let mut descriptor = MetricDescriptor::new().set_metric_kind(kind).set_value_type(value_type);
+        PrometheusConfig::default().apply(name, unit, true, &mut descriptor);
+        (descriptor.r#type, descriptor.value_type)
+    }
+
+    #[test]
+    fn test_metric_type() {
+        assert_eq!(
+            metric_type(
+                "http.server.duration",
+                "s",
+                MetricKind::Cumulative,
+                ValueType::Distribution
+            ),
+            (
+                "prometheus.googleapis.com/http_server_duration_seconds/histogram".to_string(),
+                ValueType::Distribution
+            )
+        );
+        assert_eq!(
+            metric_type("requests", "{request}", MetricKind::Cumulative, ValueType::Int64),
+            (
+                "prometheus.googleapis.com/requests_total/counter".to_string(),
+                ValueType::Double
+            )
+        );
+        assert_eq!(
+            metric_type("io_bytes_total", "By", MetricKind::Cumulative, ValueType::Double).0,
+            "prometheus.googleapis.com/io_bytes_total/counter"
+        );
+        assert_eq!(
+            metric_type("cpu.utilization", "1", MetricKind::Gauge, ValueType::Double).0,
+            "prometheus.googleapis.com/cpu_utilization_ratio/gauge"
+        );
+        assert_eq!(
+            metric_type("throughput", "By/s", MetricKind::Gauge, ValueType::Int64).0,
+            "prometheus.googleapis.com/throughput_bytes_per_second/gauge"
+        );
+        assert_eq!(
+            metric_type("2xx", "", MetricKind::Gauge, ValueType::Double).0,
+            "prometheus.googleapis.com/_2xx/gauge"
+        );
+    }
+
+    #[test]
+    fn test_metric_type_up_down_counter_as_cumulative() {
+        let mut descriptor = MetricDescriptor::new()
+            .set_metric_kind(MetricKind::Cumulative)
+            .set_value_type(ValueType::Int64);
+        PrometheusConfig::default().apply("queue.size", "{item}", false, &mut descriptor);
+        assert_eq!(descriptor.r#type, "prometheus.googleapis.com/queue_size/gauge");
+        assert_eq!(descriptor.metric_kind, MetricKind::Cumulative);
+    }
+
+    #[test]
+    fn test_prometheus_target_mapper() {
+        let resource = Resource::builder_empty()
+            .with_attributes(vec![
+                opentelemetry::KeyValue::new("service.name", "checkout"),
+                opentelemetry::KeyValue::new("service.namespace", "shop"),
+                opentelemetry::KeyValue::new("k8s.cluster.name", "prod"),
+                opentelemetry::KeyValue::new("k8s.namespace.name", "shop-ns"),
+                opentelemetry::KeyValue::new("k8s.pod.name", "checkout-7d9f"),
+                opentelemetry::KeyValue::new("cloud.availability_zone", "europe-west1-b"),
+            ])
+            .build();
+        assert_eq!(
+            PrometheusTargetMapper.map(&resource),
+            Some(MonitoredResourceDataConfig {
+                r#type: "prometheus_target".to_string(),
+                labels: HashMap::from([
+                    ("location".to_string(), "europe-west1-b".to_string()),
+                    ("cluster".to_string(), "prod".to_string()),
+                    ("namespace".to_string(), "shop-ns".to_string()),
+                    ("job".to_string(), "shop/checkout".to_string()),
+                    ("instance".to_string(), "checkout-7d9f".to_string()),
+                ]),
+            })
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use std::{fmt, sync::Arc};
+
+use google_cloud_monitoring_v3::model::TimeSeries;
+use opentelemetry_sdk::Resource;
+
+use super::utils::normalize_label_key;
+
+/// Custom [`ProjectRouting`], returns the project of a time series or `None`
+/// for the exporter project.
+pub type ProjectRouterFn = Arc<dyn Fn(&Resource, &TimeSeries) -> Option<String> + Send + Sync>;
+
+/// Picks the project each time series is written to.
+///
+/// Time series without a project, e.g. when the attribute is missing, go to
+/// the exporter project. Metric descriptors are created in every project
+/// that receives a time series of the metric.
+#[derive(Clone, Default)]
+pub enum ProjectRouting {
+    /// Every time series goes to the exporter project.
+    #[default]
+    ExporterProject,
+    /// Value of this OpenTelemetry resource attribute.
+    ResourceAttribute(String),
+    /// Value of this data point attribute, it is kept as a metric label.
+    MetricAttribute(String),
+    /// `project_id` label of the monitored resource of the time series.
+    MonitoredResourceLabel,
+    Custom(ProjectRouterFn),
+}
+
+impl fmt::Debug for ProjectRouting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExporterProject => f.write_str("ExporterProject"),
+            Self::ResourceAttribute(key) => f.debug_tuple("ResourceAttribute").field(key).finish(),
+            Self::MetricAttribute(key) => f.debug_tuple("MetricAttribute").field(key).finish(),
+            Self::MonitoredResourceLabel => f.write_str("MonitoredResourceLabel"),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// Custom routers are equal when they are clones of the same function.
+impl PartialEq for ProjectRouting {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::ExporterProject, Self::ExporterProject)
+            | (Self::MonitoredResourceLabel, Self::MonitoredResourceLabel) => true,
+            (Self::ResourceAttribute(key), Self::ResourceAttribute(other_key))
+            | (Self::MetricAttribute(key), Self::MetricAttribute(other_key)) => key == other_key,
+            (Self::Custom(router), Self::Custom(other_router)) => Arc::ptr_eq(router, other_router),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ProjectRouting {}
+
+impl ProjectRouting {
+    pub(crate) fn is_exporter_project(&self) -> bool {
+        matches!(self, Self::ExporterProject)
+    }
+
+    /// Destination project of `time_series`, `None` for the exporter project.
+    pub(crate) fn route(&self, resource: &Resource, time_series: &TimeSeries) -> Option<String> {
+        let project_id = match self {
+            Self::ExporterProject => None,
+            Self::ResourceAttribute(key) => resource
+                .get(&opentelemetry::Key::new(key.clone()))
+                .map(|value| value.to_string()),
+            Self::MetricAttribute(key) => time_series
+                .metric
+                .as_ref()
+                .and_then(|metric| metric.labels.get(&normalize_label_key(key)).cloned()),
+            Self::MonitoredResourceLabel => time_series
+                .resource
+                .as_ref()
+                .and_then(|resource| resource.labels.get("project_id").cloned()),
+            Self::Custom(router) => router(resource, time_series),
+        };
+        project_id.filter(|project_id| !project_id.is_empty())
+    }
+}
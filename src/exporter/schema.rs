@@ -0,0 +1,440 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Mutex,
+};
+
+use google_cloud_api::model::{
+    LabelDescriptor, MetricDescriptor, label_descriptor,
+    metric_descriptor::{MetricKind, ValueType},
+};
+use google_cloud_monitoring_v3::model::TimeSeries;
+
+use super::GCPMetricsExporterInitError;
+
+/// Metric definitions checked into the repository, used instead of the
+/// descriptors inferred from the instruments.
+///
+/// ```yaml
+/// undeclared: reject
+/// metrics:
+///   - name: http.server.requests
+///     metric_kind: CUMULATIVE
+///     value_type: INT64
+///     unit: "{request}"
+///     description: Handled HTTP requests.
+///     labels:
+///       - key: http_route
+///         description: Route template of the request.
+///     monitored_resource_types: [k8s_container]
+/// ```
+///
+/// The metric type is `{prefix}/{name}` unless `type` is set. A declared
+/// metric whose kind or value type does not match its instrument is dropped.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricSchema {
+    #[serde(default)]
+    pub undeclared: UndeclaredPolicy,
+    #[serde(default)]
+    pub metrics: Vec<MetricSchemaEntry>,
+}
+
+/// What the exporter does with metrics, labels and monitored resource types
+/// missing from the [`MetricSchema`]. A warning is logged once either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UndeclaredPolicy {
+    /// Undeclared metrics are exported with an inferred descriptor,
+    /// undeclared labels are kept and added to the declared descriptor as
+    /// `STRING` labels.
+    #[default]
+    Warn,
+    /// Undeclared metrics and the time series with undeclared labels or
+    /// monitored resource types are dropped.
+    Reject,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricSchemaEntry {
+    /// Instrument name, e.g. `http.server.requests`.
+    pub name: String,
+    /// Metric type, `{prefix}/{name}` if not set.
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub metric_type: Option<String>,
+    pub metric_kind: MetricKind,
+    pub value_type: ValueType,
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub description: String,
+    /// `name` if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<MetricSchemaLabel>,
+    /// Monitored resource types the metric is written to, any if empty.
+    #[serde(default)]
+    pub monitored_resource_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricSchemaLabel {
+    /// Label key as written, i.e. `http_route` for an `http.route` attribute.
+    pub key: String,
+    #[serde(default)]
+    pub description: String,
+    /// `STRING` by default.
+    #[serde(default)]
+    pub value_type: label_descriptor::ValueType,
+}
+
+impl MetricSchema {
+    /// Loads a `.json`, `.yaml`/`.yml` (`schema-yaml` feature) or `.toml`
+    /// (`schema-toml` feature) file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, GCPMetricsExporterInitError> {
+        let path = path.as_ref();
+        let content =
+            std::fs::read_to_string(path).map_err(|err| GCPMetricsExporterInitError::LoadSchema(err.into()))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&content),
+            #[cfg(feature = "schema-yaml")]
+            Some("yaml" | "yml") => Self::from_yaml(&content),
+            #[cfg(feature = "schema-toml")]
+            Some("toml") => Self::from_toml(&content),
+            _ => Err(GCPMetricsExporterInitError::LoadSchema(
+                format!("unsupported schema file {:?}", path).into(),
+            )),
+        }
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, GCPMetricsExporterInitError> {
+        serde_json::from_str(content).map_err(|err| GCPMetricsExporterInitError::LoadSchema(err.into()))
+    }
+
+    #[cfg(feature = "schema-yaml")]
+    pub fn from_yaml(content: &str) -> Result<Self, GCPMetricsExporterInitError> {
+        serde_yaml::from_str(content).map_err(|err| GCPMetricsExporterInitError::LoadSchema(err.into()))
+    }
+
+    #[cfg(feature = "schema-toml")]
+    pub fn from_toml(content: &str) -> Result<Self, GCPMetricsExporterInitError> {
+        toml::from_str(content).map_err(|err| GCPMetricsExporterInitError::LoadSchema(err.into()))
+    }
+
+    /// Descriptors of the declared metrics, e.g. to create them ahead of the
+    /// first export.
+    pub fn metric_descriptors(&self, prefix: &str) -> Vec<MetricDescriptor> {
+        self.metrics
+            .iter()
+            .map(|entry| entry.metric_descriptor(prefix))
+            .collect()
+    }
+
+    /// Names and label keys are unique and not empty, kinds and value types are known.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for entry in &self.metrics {
+            if entry.name.is_empty() {
+                return Err("schema metric names must not be empty".to_string());
+            }
+            if !names.insert(entry.name.as_str()) {
+                return Err(format!("schema metric {:?} is declared twice", entry.name));
+            }
+            if !matches!(
+                entry.metric_kind,
+                MetricKind::Gauge | MetricKind::Delta | MetricKind::Cumulative
+            ) {
+                return Err(format!(
+                    "schema metric {:?} has an invalid metric_kind {:?}",
+                    entry.name, entry.metric_kind
+                ));
+            }
+            if !matches!(
+                entry.value_type,
+                ValueType::Bool | ValueType::Int64 | ValueType::Double | ValueType::String | ValueType::Distribution
+            ) {
+                return Err(format!(
+                    "schema metric {:?} has an invalid value_type {:?}",
+                    entry.name, entry.value_type
+                ));
+            }
+            let mut keys = HashSet::new();
+            for label in &entry.labels {
+                if label.key.is_empty() || !keys.insert(label.key.as_str()) {
+                    return Err(format!(
+                        "schema metric {:?} has an empty or duplicate label {:?}",
+                        entry.name, label.key
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MetricSchemaEntry {
+    pub fn metric_descriptor(&self, prefix: &str) -> MetricDescriptor {
+        MetricDescriptor::new()
+            .set_type(
+                self.metric_type
+                    .clone()
+                    .unwrap_or_else(|| format!("{}/{}", prefix, self.name)),
+            )
+            .set_display_name(self.display_name.clone().unwrap_or_else(|| self.name.clone()))
+            .set_description(self.description.clone())
+            .set_unit(self.unit.clone())
+            .set_metric_kind(self.metric_kind.clone())
+            .set_value_type(self.value_type.clone())
+            .set_labels(self.labels.iter().map(|label| {
+                LabelDescriptor::new()
+                    .set_key(label.key.clone())
+                    .set_description(label.description.clone())
+                    .set_value_type(label.value_type.clone())
+            }))
+    }
+
+    /// Points of the instrument described by `inferred` can be written as declared,
+    /// integers can be written as doubles.
+    fn matches(&self, inferred: &MetricDescriptor) -> bool {
+        let value_type_matches = self.value_type == inferred.value_type
+            || (self.value_type == ValueType::Double && inferred.value_type == ValueType::Int64);
+        self.metric_kind == inferred.metric_kind && value_type_matches
+    }
+}
+
+/// How a metric relates to the schema, see [`SchemaCheck::apply`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SchemaMatch {
+    Declared,
+    Undeclared,
+    Mismatch,
+}
+
+/// [`MetricSchema`] indexed by metric name, with the warnings already logged.
+#[derive(Debug)]
+pub(crate) struct SchemaCheck {
+    undeclared: UndeclaredPolicy,
+    metrics: HashMap<String, MetricSchemaEntry>,
+    /// Label key of the unique identifier, allowed on every metric.
+    unique_identifier_key: Option<String>,
+    warned: Mutex<HashSet<String>>,
+}
+
+impl SchemaCheck {
+    pub(crate) fn new(schema: MetricSchema, unique_identifier_key: Option<String>) -> Self {
+        Self {
+            undeclared: schema.undeclared,
+            metrics: schema
+                .metrics
+                .into_iter()
+                .map(|entry| (entry.name.clone(), entry))
+                .collect(),
+            unique_identifier_key,
+            warned: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Replaces the inferred `descriptor` of the metric `name` by the declared
+    /// one. The unique identifier label is kept, and the undeclared labels
+    /// too with [`UndeclaredPolicy::Warn`], the time series keep them.
+    pub(crate) fn apply(&self, name: &str, prefix: &str, descriptor: &mut MetricDescriptor) -> SchemaMatch {
+        let Some(entry) = self.metrics.get(name) else {
+            return SchemaMatch::Undeclared;
+        };
+        if !entry.matches(descriptor) {
+            return SchemaMatch::Mismatch;
+        }
+        let inferred_labels = std::mem::take(&mut descriptor.labels);
+        *descriptor = entry.metric_descriptor(prefix);
+        for label in inferred_labels {
+            let kept =
+                self.undeclared == UndeclaredPolicy::Warn || Some(&label.key) == self.unique_identifier_key.as_ref();
+            if kept && !descriptor.labels.iter().any(|declared| declared.key == label.key) {
+                descriptor.labels.push(label);
+            }
+        }
+        SchemaMatch::Declared
+    }
+
+    /// Removes the time series of the metric `name` the schema does not allow,
+    /// undeclared labels are only logged with [`UndeclaredPolicy::Warn`].
+    pub(crate) fn check_time_series(&self, name: &str, schema_match: SchemaMatch, all_series: &mut Vec<TimeSeries>) {
+        let reject = self.undeclared == UndeclaredPolicy::Reject;
+        let entry = match (schema_match, self.metrics.get(name)) {
+            (SchemaMatch::Declared, Some(entry)) => entry,
+            (SchemaMatch::Mismatch, Some(entry)) => {
+                self.warn_once(format!(
+                    "metric {} does not match its {:?} {:?} schema declaration, dropping it",
+                    name, entry.metric_kind, entry.value_type
+                ));
+                all_series.clear();
+                return;
+            }
+            _ => {
+                self.warn_once(format!("metric {} is not declared in the schema", name));
+                if reject {
+                    all_series.clear();
+                }
+                return;
+            }
+        };
+        let keys = entry
+            .labels
+            .iter()
+            .map(|label| label.key.as_str())
+            .chain(self.unique_identifier_key.as_deref())
+            .collect::<HashSet<_>>();
+        all_series.retain(|series| {
+            if let Some(resource) = &series.resource {
+                if !entry.monitored_resource_types.is_empty()
+                    && !entry.monitored_resource_types.contains(&resource.r#type)
+                {
+                    self.warn_once(format!(
+                        "monitored resource type {} of metric {} is not declared in the schema",
+                        resource.r#type, name
+                    ));
+                    if reject {
+                        return false;
+                    }
+                }
+            }
+            let Some(metric) = series.metric.as_ref() else {
+                return true;
+            };
+            for key in metric.labels.keys() {
+                if !keys.contains(key.as_str()) {
+                    self.warn_once(format!(
+                        "label {} of metric {} is not declared in the schema",
+                        key, name
+                    ));
+                }
+            }
+            !reject || metric.labels.keys().all(|key| keys.contains(key.as_str()))
+        });
+    }
+
+    fn warn_once(&self, message: String) {
+        let mut warned = self.warned.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !warned.contains(&message) {
+            tracing::warn!("GCPMetricsExporter: {}", message);
+            warned.insert(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    const SCHEMA_JSON: &str = r#"{
+        "undeclared": "reject",
+        "metrics": [{
+            "name": "requests",
+            "metric_kind": "CUMULATIVE",
+            "value_type": "INT64",
+            "unit": "{request}",
+            "description": "Handled requests.",
+            "labels": [{"key": "route", "description": "Route template."}]
+        }]
+    }"#;
+
+    #[test]
+    fn test_schema_from_json() {
+        let schema = MetricSchema::from_json(SCHEMA_JSON).unwrap();
+        assert_eq!(schema.undeclared, UndeclaredPolicy::Reject);
+        assert!(schema.validate().is_ok());
+        assert_eq!(
+            schema.metric_descriptors("workload.googleapis.com"),
+            vec![
+                MetricDescriptor::new()
+                    .set_type("workload.googleapis.com/requests")
+                    .set_display_name("requests")
+                    .set_description("Handled requests.")
+                    .set_unit("{request}")
+                    .set_metric_kind(MetricKind::Cumulative)
+                    .set_value_type(ValueType::Int64)
+                    .set_labels([LabelDescriptor::new()
+                        .set_key("route")
+                        .set_description("Route template.")])
+            ]
+        );
+        assert!(MetricSchema::from_json(r#"{"metrics": [], "unknown": 1}"#).is_err());
+    }
+
+    #[cfg(feature = "schema-yaml")]
+    #[test]
+    fn test_schema_from_yaml() {
+        let schema = MetricSchema::from_yaml(
+            r#"
+undeclared: reject
+metrics:
+  - name: requests
+    metric_kind: CUMULATIVE
+    value_type: INT64
+    unit: "{request}"
+    description: Handled requests.
+    labels:
+      - key: route
+        description: Route template.
+"#,
+        )
+        .unwrap();
+        assert_eq!(schema, MetricSchema::from_json(SCHEMA_JSON).unwrap());
+    }
+
+    #[cfg(feature = "schema-toml")]
+    #[test]
+    fn test_schema_from_toml() {
+        let schema = MetricSchema::from_toml(
+            r#"
+undeclared = "reject"
+
+[[metrics]]
+name = "requests"
+metric_kind = "CUMULATIVE"
+value_type = "INT64"
+unit = "{request}"
+description = "Handled requests."
+labels = [{ key = "route", description = "Route template." }]
+"#,
+        )
+        .unwrap();
+        assert_eq!(schema, MetricSchema::from_json(SCHEMA_JSON).unwrap());
+    }
+
+    #[test]
+    fn test_validate_schema() {
+        let entry = MetricSchemaEntry {
+            name: "requests".to_string(),
+            metric_kind: MetricKind::Cumulative,
+            value_type: ValueType::Int64,
+            ..Default::default()
+        };
+        let schema = |metrics: Vec<MetricSchemaEntry>| MetricSchema {
+            metrics,
+            ..Default::default()
+        };
+        assert!(schema(vec![entry.clone()]).validate().is_ok());
+        assert!(schema(vec![entry.clone(), entry.clone()]).validate().is_err());
+        let unspecified_kind = MetricSchemaEntry {
+            metric_kind: MetricKind::Unspecified,
+            ..entry.clone()
+        };
+        assert!(schema(vec![unspecified_kind]).validate().is_err());
+        let duplicate_label = MetricSchemaEntry {
+            labels: vec![
+                MetricSchemaLabel {
+                    key: "route".to_string(),
+                    ..Default::default()
+                };
+                2
+            ],
+            ..entry
+        };
+        assert!(schema(vec![duplicate_label]).validate().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use opentelemetry::{
+    KeyValue,
+    metrics::{Counter, Histogram, Meter},
+};
+use std::{sync::Mutex, time::Duration};
+
+use super::{error::GCPMetricsExportError, stats::ExportStats};
+
+/// Outcome of a `CreateMetricDescriptor` call, recorded as the `outcome` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DescriptorOutcome {
+    Created,
+    AlreadyExists,
+    PermissionDenied,
+    Error,
+}
+
+impl DescriptorOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DescriptorOutcome::Created => "created",
+            DescriptorOutcome::AlreadyExists => "already_exists",
+            DescriptorOutcome::PermissionDenied => "permission_denied",
+            DescriptorOutcome::Error => "error",
+        }
+    }
+}
+
+struct Instruments {
+    export_duration: Histogram<f64>,
+    time_series_sent: Counter<u64>,
+    time_series_failed: Counter<u64>,
+    time_series_dropped: Counter<u64>,
+    metric_descriptor_create: Counter<u64>,
+    retries: Counter<u64>,
+    cumulative_resets: Counter<u64>,
+}
+
+/// Metrics the exporter records about itself through a user supplied [`Meter`],
+/// and the [`ExportStats`] snapshot. Instruments are a no-op when no meter was configured.
+pub(crate) struct ExporterMetrics {
+    instruments: Option<Instruments>,
+    stats: Mutex<ExportStats>,
+}
+
+impl ExporterMetrics {
+    pub(crate) fn new(meter: Option<&Meter>) -> Self {
+        let instruments = meter.map(|meter| Instruments {
+            export_duration: meter
+                .f64_histogram("gcp_metrics_exporter.export.duration")
+                .with_description("Duration of exports to Google Cloud Monitoring.")
+                .with_unit("s")
+                .build(),
+            time_series_sent: meter
+                .u64_counter("gcp_metrics_exporter.time_series.sent")
+                .with_description("Time series accepted by Google Cloud Monitoring.")
+                .with_unit("{time_series}")
+                .build(),
+            time_series_failed: meter
+                .u64_counter("gcp_metrics_exporter.time_series.failed")
+                .with_description("Time series in CreateTimeSeries calls that returned an error.")
+                .with_unit("{time_series}")
+                .build(),
+            time_series_dropped: meter
+                .u64_counter("gcp_metrics_exporter.time_series.dropped")
+                .with_description("Time series given up on, they will not be sent again.")
+                .with_unit("{time_series}")
+                .build(),
+            metric_descriptor_create: meter
+                .u64_counter("gcp_metrics_exporter.metric_descriptor.create")
+                .with_description("CreateMetricDescriptor calls by outcome.")
+                .with_unit("{call}")
+                .build(),
+            retries: meter
+                .u64_counter("gcp_metrics_exporter.retries")
+                .with_description("Attempts to resend time series that failed before.")
+                .with_unit("{call}")
+                .build(),
+            cumulative_resets: meter
+                .u64_counter("gcp_metrics_exporter.cumulative_resets")
+                .with_description("Cumulative time series that started a new interval.")
+                .with_unit("{time_series}")
+                .build(),
+        });
+        Self {
+            instruments,
+            stats: Mutex::new(ExportStats::default()),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ExportStats {
+        self.lock_stats().clone()
+    }
+
+    fn lock_stats(&self) -> std::sync::MutexGuard<'_, ExportStats> {
+        self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Counters to pass to [`ExporterMetrics::record_export`] once the export is over.
+    pub(crate) fn start_export(&self) -> [u64; 3] {
+        self.lock_stats().counters()
+    }
+
+    pub(crate) fn record_export(&self, started: [u64; 3], duration: Duration, success: bool) {
+        self.lock_stats().record_export(started, success);
+        if let Some(instruments) = &self.instruments {
+            instruments.export_duration.record(
+                duration.as_secs_f64(),
+                &[KeyValue::new("outcome", if success { "success" } else { "failure" })],
+            );
+        }
+    }
+
+    pub(crate) fn add_sent(&self, count: usize) {
+        self.lock_stats().time_series_sent += count as u64;
+        if let Some(instruments) = &self.instruments {
+            instruments.time_series_sent.add(count as u64, &[]);
+        }
+    }
+
+    pub(crate) fn add_failed(&self, count: usize) {
+        self.lock_stats().time_series_failed += count as u64;
+        if let Some(instruments) = &self.instruments {
+            instruments.time_series_failed.add(count as u64, &[]);
+        }
+    }
+
+    pub(crate) fn add_dropped(&self, count: usize) {
+        self.lock_stats().time_series_dropped += count as u64;
+        if let Some(instruments) = &self.instruments {
+            instruments.time_series_dropped.add(count as u64, &[]);
+        }
+    }
+
+    pub(crate) fn add_cumulative_resets(&self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        self.lock_stats().cumulative_resets += count as u64;
+        if let Some(instruments) = &self.instruments {
+            instruments.cumulative_resets.add(count as u64, &[]);
+        }
+    }
+
+    pub(crate) fn record_descriptor(&self, outcome: DescriptorOutcome) {
+        if let Some(instruments) = &self.instruments {
+            instruments
+                .metric_descriptor_create
+                .add(1, &[KeyValue::new("outcome", outcome.as_str())]);
+        }
+    }
+
+    pub(crate) fn record_error(&self, err: GCPMetricsExportError) {
+        self.lock_stats().record_error(err);
+    }
+
+    pub(crate) fn set_cached_metric_descriptors(&self, count: usize) {
+        self.lock_stats().cached_metric_descriptors = count;
+    }
+
+    #[cfg_attr(not(feature = "spool"), allow(dead_code))]
+    pub(crate) fn set_queue_depth(&self, depth: usize) {
+        self.lock_stats().queue_depth = depth;
+    }
+
+    #[cfg_attr(not(feature = "spool"), allow(dead_code))]
+    pub(crate) fn add_retry(&self) {
+        if let Some(instruments) = &self.instruments {
+            instruments.retries.add(1, &[]);
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tokio::sync::Mutex;
+
+use super::utils::MAX_POINT_AGE;
+
+const SPOOL_FILE_EXTENSION: &str = "json";
+
+/// Configuration of the on-disk buffer used to keep time series batches
+/// that could not be delivered to Google Cloud Monitoring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpoolConfig {
+    /// directory: where spooled batches are written, one file per
+    ///     `CreateTimeSeriesRequest`. Created on first use. No default: the
+    ///     directory must outlive the process to replay its batches after a
+    ///     restart, and two processes must not share it.
+    pub directory: PathBuf,
+    /// max_size_bytes: upper bound for the total size of spooled files.
+    ///     The oldest batches are discarded first when it is exceeded.
+    pub max_size_bytes: u64,
+    /// max_age: spooled batches older than this are discarded without
+    ///     being replayed.
+    pub max_age: Duration,
+}
+
+impl SpoolConfig {
+    /// Spool in `directory`, up to 64 MiB of batches of the last 24 hours.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            max_size_bytes: 64 * 1024 * 1024,
+            max_age: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SpoolEntry {
+    path: PathBuf,
+    sequence: u64,
+    created_at_millis: u128,
+    size: u64,
+}
+
+/// File-backed FIFO of serialized `CreateTimeSeriesRequest` batches.
+///
+/// File names are `{sequence}-{created_at_millis}.json`, so the replay order
+/// and the age of a batch are known without reading it.
+pub(crate) struct Spool {
+    config: SpoolConfig,
+    next_sequence: Mutex<Option<u64>>,
+}
+
+impl Spool {
+    pub(crate) fn new(config: SpoolConfig) -> Self {
+        Self {
+            config,
+            next_sequence: Mutex::new(None),
+        }
+    }
+
+    /// Persists a batch at the end of the queue and enforces the size and age limits.
+    pub(crate) async fn push(
+        &self,
+        req: &google_cloud_monitoring_v3::model::CreateTimeSeriesRequest,
+    ) -> io::Result<()> {
+        let mut next_sequence = self.next_sequence.lock().await;
+        let sequence = match *next_sequence {
+            Some(sequence) => sequence,
+            None => {
+                tokio::fs::create_dir_all(&self.config.directory).await?;
+                self.entries()
+                    .await?
+                    .last()
+                    .map(|entry| entry.sequence + 1)
+                    .unwrap_or_default()
+            }
+        };
+        let body = serde_json::to_vec(req).map_err(io::Error::other)?;
+        let path = self.config.directory.join(format!(
+            "{:020}-{}.{}",
+            sequence,
+            unix_millis(SystemTime::now()),
+            SPOOL_FILE_EXTENSION
+        ));
+        tokio::fs::write(&path, body).await?;
+        *next_sequence = Some(sequence + 1);
+        drop(next_sequence);
+        self.enforce_limits().await
+    }
+
+    /// Returns the spooled batches in the order they were written, after
+    /// discarding the ones that are over the configured limits.
+    pub(crate) async fn pending(&self) -> io::Result<Vec<SpoolEntry>> {
+        self.enforce_limits().await?;
+        self.entries().await
+    }
+
+    /// Reads a spooled batch, dropping the time series Cloud Monitoring would
+    /// no longer accept. Returns `None` when nothing is left to send.
+    pub(crate) async fn load(
+        &self,
+        entry: &SpoolEntry,
+    ) -> io::Result<Option<google_cloud_monitoring_v3::model::CreateTimeSeriesRequest>> {
+        let body = tokio::fs::read(&entry.path).await?;
+        let mut req = match serde_json::from_slice::<google_cloud_monitoring_v3::model::CreateTimeSeriesRequest>(&body)
+        {
+            Ok(req) => req,
+            Err(err) => {
+                tracing::warn!(
+                    "GCPMetricsExporter: discarding unreadable spool file {:?}: {}",
+                    entry.path,
+                    err
+                );
+                return Ok(None);
+            }
+        };
+        let oldest_accepted = SystemTime::now()
+            .checked_sub(MAX_POINT_AGE)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        req.time_series
+            .retain(|time_series| is_accepted(time_series, oldest_accepted));
+        if req.time_series.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(req))
+    }
+
+    pub(crate) async fn remove(&self, entry: &SpoolEntry) -> io::Result<()> {
+        match tokio::fs::remove_file(&entry.path).await {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    async fn enforce_limits(&self) -> io::Result<()> {
+        let entries = self.entries().await?;
+        let now = unix_millis(SystemTime::now());
+        let max_age = self.config.max_age.as_millis();
+        let mut total_size: u64 = entries.iter().map(|entry| entry.size).sum();
+        for entry in &entries {
+            let expired = now.saturating_sub(entry.created_at_millis) > max_age;
+            let over_size = total_size > self.config.max_size_bytes;
+            if !expired && !over_size {
+                continue;
+            }
+            tracing::warn!(
+                "GCPMetricsExporter: discarding spooled batch {:?} ({})",
+                entry.path,
+                if expired { "too old" } else { "spool is full" }
+            );
+            self.remove(entry).await?;
+            total_size -= entry.size;
+        }
+        Ok(())
+    }
+
+    async fn entries(&self) -> io::Result<Vec<SpoolEntry>> {
+        let mut entries = Vec::new();
+        let mut dir = match tokio::fs::read_dir(&self.config.directory).await {
+            Ok(dir) => dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(entries),
+            Err(err) => return Err(err),
+        };
+        while let Some(dir_entry) = dir.next_entry().await? {
+            let path = dir_entry.path();
+            let Some((sequence, created_at_millis)) = parse_file_name(&path) else {
+                continue;
+            };
+            entries.push(SpoolEntry {
+                size: dir_entry.metadata().await?.len(),
+                path,
+                sequence,
+                created_at_millis,
+            });
+        }
+        entries.sort_by_key(|entry| entry.sequence);
+        Ok(entries)
+    }
+}
+
+fn parse_file_name(path: &Path) -> Option<(u64, u128)> {
+    if path.extension()? != SPOOL_FILE_EXTENSION {
+        return None;
+    }
+    let (sequence, created_at_millis) = path.file_stem()?.to_str()?.split_once('-')?;
+    Some((sequence.parse().ok()?, created_at_millis.parse().ok()?))
+}
+
+fn unix_millis(time: SystemTime) -> u128 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+fn is_accepted(time_series: &google_cloud_monitoring_v3::model::TimeSeries, oldest_accepted: SystemTime) -> bool {
+    let oldest_accepted = oldest_accepted
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    time_series.points.iter().all(|point| {
+        point
+            .interval
+            .as_ref()
+            .and_then(|interval| interval.end_time.as_ref())
+            .is_some_and(|end_time| end_time.seconds() >= oldest_accepted)
+    })
+}
+
+/// Errors worth keeping a batch for: the request may succeed later without
+/// any change on our side.
+pub(crate) fn is_retryable(err: &google_cloud_gax::error::Error) -> bool {
+    use google_cloud_gax::error::rpc::Code;
+    match err.status() {
+        Some(status) => matches!(
+            status.code,
+            Code::Unavailable | Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted | Code::Internal
+        ),
+        None => err.is_timeout() || err.is_io() || err.is_connect() || err.is_transport() || err.is_exhausted(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_spool(directory: &TempDir, max_size_bytes: u64, max_age: Duration) -> Spool {
+        Spool::new(SpoolConfig {
+            directory: directory.path().to_path_buf(),
+            max_size_bytes,
+            max_age,
+        })
+    }
+
+    fn request(end_time: SystemTime) -> google_cloud_monitoring_v3::model::CreateTimeSeriesRequest {
+        let end_time = end_time.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+        google_cloud_monitoring_v3::model::CreateTimeSeriesRequest::new()
+            .set_name("projects/fake_project_id")
+            .set_time_series([google_cloud_monitoring_v3::model::TimeSeries::new().set_points([
+                google_cloud_monitoring_v3::model::Point::new().set_interval(
+                    google_cloud_monitoring_v3::model::TimeInterval::new()
+                        .set_end_time(google_cloud_wkt::Timestamp::new(end_time, 0).unwrap()),
+                ),
+            ])])
+    }
+
+    #[tokio::test]
+    async fn test_spool_replays_in_order() {
+        let directory = TempDir::new().unwrap();
+        let spool = test_spool(&directory, u64::MAX, Duration::from_secs(60));
+        let now = SystemTime::now();
+        for offset in [3, 2, 1] {
+            spool.push(&request(now - Duration::from_secs(offset))).await.unwrap();
+        }
+
+        let pending = spool.pending().await.unwrap();
+        assert_eq!(pending.len(), 3);
+        let mut loaded = Vec::new();
+        for entry in &pending {
+            loaded.push(spool.load(entry).await.unwrap().unwrap());
+            spool.remove(entry).await.unwrap();
+        }
+        assert_eq!(
+            loaded,
+            vec![
+                request(now - Duration::from_secs(3)),
+                request(now - Duration::from_secs(2)),
+                request(now - Duration::from_secs(1)),
+            ]
+        );
+        assert!(spool.pending().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spool_discards_oldest_when_full() {
+        let batch_size = serde_json::to_vec(&request(SystemTime::now())).unwrap().len() as u64;
+        let directory = TempDir::new().unwrap();
+        let spool = test_spool(&directory, batch_size * 2, Duration::from_secs(60));
+        for _ in 0..3 {
+            spool.push(&request(SystemTime::now())).await.unwrap();
+        }
+
+        let pending = spool.pending().await.unwrap();
+        assert_eq!(
+            pending.iter().map(|entry| entry.sequence).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spool_discards_expired_batches() {
+        let directory = TempDir::new().unwrap();
+        let spool = test_spool(&directory, u64::MAX, Duration::ZERO);
+        spool.push(&request(SystemTime::now())).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        assert!(spool.pending().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spool_drops_points_too_old_for_cloud_monitoring() {
+        let directory = TempDir::new().unwrap();
+        let spool = test_spool(&directory, u64::MAX, Duration::from_secs(60));
+        spool
+            .push(&request(SystemTime::now() - MAX_POINT_AGE - Duration::from_secs(60)))
+            .await
+            .unwrap();
+
+        let pending = spool.pending().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(spool.load(&pending[0]).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,108 @@
+use std::{sync::Arc, time::SystemTime};
+
+use super::{
+    error::{ExportOperation, GCPMetricsExportError},
+    self_metrics::ExporterMetrics,
+};
+
+/// Result of the last export, as seen in [`ExportStats::last_export_outcome`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportOutcome {
+    /// Every time series of the export was accepted.
+    Success,
+    /// Some time series were accepted, others failed or were dropped.
+    PartialFailure,
+    /// Nothing was delivered, or the export returned an error.
+    Failure,
+}
+
+/// Snapshot of the exporter state returned by [`crate::GCPMetricsExporter::stats`].
+///
+/// Meant for health checks, e.g. mark a pod unhealthy when
+/// `last_success_time` is older than a few export intervals.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportStats {
+    /// When the last export finished.
+    pub last_export_time: Option<SystemTime>,
+    /// Outcome of the last export.
+    pub last_export_outcome: Option<ExportOutcome>,
+    /// When the last export with outcome [`ExportOutcome::Success`] finished.
+    pub last_success_time: Option<SystemTime>,
+    /// Last error returned by `CreateMetricDescriptor`.
+    pub last_metric_descriptor_error: Option<GCPMetricsExportError>,
+    /// Last error returned by `CreateTimeSeries`.
+    pub last_time_series_error: Option<GCPMetricsExportError>,
+    /// Last error reading or writing the on-disk spool.
+    pub last_spool_error: Option<GCPMetricsExportError>,
+    /// Time series accepted by Cloud Monitoring since the exporter was created.
+    pub time_series_sent: u64,
+    /// Time series in `CreateTimeSeries` calls that returned an error since the exporter was created.
+    pub time_series_failed: u64,
+    /// Time series given up on since the exporter was created, they will not be sent again.
+    pub time_series_dropped: u64,
+    /// Cumulative time series that started a new interval since the exporter
+    /// was created, see [`crate::GCPMetricsExporterConfig::handle_cumulative_resets`].
+    pub cumulative_resets: u64,
+    /// Metric descriptors currently cached by the exporter.
+    pub cached_metric_descriptors: usize,
+    /// Batches waiting in the on-disk spool. Always 0 without the `spool` feature.
+    pub queue_depth: usize,
+}
+
+/// Cloneable access to the [`ExportStats`] of an exporter, e.g. from a health endpoint.
+#[derive(Clone)]
+pub struct ExportStatsHandle {
+    metrics: Arc<ExporterMetrics>,
+}
+
+impl ExportStatsHandle {
+    pub(crate) fn new(metrics: Arc<ExporterMetrics>) -> Self {
+        Self { metrics }
+    }
+
+    /// Returns a snapshot of the export statistics.
+    pub fn stats(&self) -> ExportStats {
+        self.metrics.stats()
+    }
+}
+
+impl std::fmt::Debug for ExportStatsHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ExportStatsHandle").field(&self.stats()).finish()
+    }
+}
+
+impl ExportStats {
+    pub(crate) fn record_error(&mut self, err: GCPMetricsExportError) {
+        let last_error = match err.details().operation {
+            ExportOperation::CreateMetricDescriptor => &mut self.last_metric_descriptor_error,
+            ExportOperation::CreateTimeSeries => &mut self.last_time_series_error,
+            ExportOperation::Spool => &mut self.last_spool_error,
+        };
+        *last_error = Some(err);
+    }
+
+    pub(crate) fn counters(&self) -> [u64; 3] {
+        [self.time_series_sent, self.time_series_failed, self.time_series_dropped]
+    }
+
+    /// Closes an export started when the counters were `before`.
+    pub(crate) fn record_export(&mut self, before: [u64; 3], success: bool) {
+        let now = SystemTime::now();
+        let [sent, failed, dropped] = self.counters();
+        let sent = sent - before[0];
+        let undelivered = (failed - before[1]) + (dropped - before[2]);
+        let outcome = if !success || (sent == 0 && undelivered > 0) {
+            ExportOutcome::Failure
+        } else if undelivered > 0 {
+            ExportOutcome::PartialFailure
+        } else {
+            ExportOutcome::Success
+        };
+        if outcome == ExportOutcome::Success {
+            self.last_success_time = Some(now);
+        }
+        self.last_export_time = Some(now);
+        self.last_export_outcome = Some(outcome);
+    }
+}
@@ -1,3 +1,5 @@
+use super::u64_policy::U64Conversion;
+
 pub trait ToF64 {
     fn to_f64(self) -> f64;
 }
@@ -20,21 +22,20 @@ impl ToF64 for i64 {
     }
 }
 
+/// INT64 value of a point of `metric_type`, u64 values follow the
+/// [`super::U64Policy`] of `u64_conversion`.
 pub trait ToI64 {
-    fn to_i64(self) -> i64;
+    fn to_i64(self, u64_conversion: &U64Conversion, metric_type: &str) -> i64;
 }
 
 impl ToI64 for i64 {
-    fn to_i64(self) -> i64 {
+    fn to_i64(self, _u64_conversion: &U64Conversion, _metric_type: &str) -> i64 {
         self
     }
 }
 
 impl ToI64 for u64 {
-    fn to_i64(self) -> i64 {
-        match i64::try_from(self) {
-            Ok(v) => v,
-            Err(_) => i64::MAX,
-        }
+    fn to_i64(self, u64_conversion: &U64Conversion, metric_type: &str) -> i64 {
+        u64_conversion.to_i64(self, metric_type)
     }
 }
@@ -0,0 +1,108 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use google_cloud_api::model::metric_descriptor::ValueType;
+
+/// Largest modulo of [`U64Policy::Wrap`], the wrapped values fit in an INT64.
+const MAX_MODULO: u64 = i64::MAX as u64 + 1;
+
+/// How the values of u64 sums and gauges are written, INT64 points only go
+/// up to `i64::MAX`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum U64Policy {
+    /// INT64 metrics, values above `i64::MAX` are written as `i64::MAX` and
+    /// logged once per metric.
+    #[default]
+    Saturate,
+    /// DOUBLE metrics, exact up to 2^53 and rounded above.
+    Double,
+    /// INT64 metrics of the value modulo `modulo`, at most 2^63. A counter
+    /// going over the modulo starts over from 0, like a reset.
+    Wrap { modulo: u64 },
+}
+
+impl U64Policy {
+    /// Value type of the metric descriptor of a u64 sum or gauge.
+    pub(crate) fn value_type(&self) -> ValueType {
+        match self {
+            U64Policy::Double => ValueType::Double,
+            U64Policy::Saturate | U64Policy::Wrap { .. } => ValueType::Int64,
+        }
+    }
+
+    /// The modulo of [`U64Policy::Wrap`] is between 1 and 2^63.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match self {
+            U64Policy::Wrap { modulo } if *modulo == 0 || *modulo > MAX_MODULO => {
+                Err(format!("u64_policy modulo must be between 1 and 2^63, got {}", modulo))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Applies a [`U64Policy`] to INT64 points, with the metrics already warned about.
+#[derive(Debug, Default)]
+pub(crate) struct U64Conversion {
+    policy: U64Policy,
+    warned: Mutex<HashSet<String>>,
+}
+
+impl U64Conversion {
+    pub(crate) fn new(policy: U64Policy) -> Self {
+        Self {
+            policy,
+            warned: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub(crate) fn policy(&self) -> U64Policy {
+        self.policy
+    }
+
+    /// INT64 value of `value`, a point of `metric_type`.
+    pub(crate) fn to_i64(&self, value: u64, metric_type: &str) -> i64 {
+        if let U64Policy::Wrap { modulo } = self.policy {
+            return (value % modulo) as i64;
+        }
+        match i64::try_from(value) {
+            Ok(value) => value,
+            Err(_) => {
+                let mut warned = self.warned.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if warned.insert(metric_type.to_string()) {
+                    tracing::warn!(
+                        "GCPMetricsExporter: {} value {} is above i64::MAX and written as i64::MAX, see U64Policy",
+                        metric_type,
+                        value
+                    );
+                }
+                i64::MAX
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    #[test]
+    fn test_u64_conversion() {
+        let big = u64::MAX - 1;
+        let saturate = U64Conversion::new(U64Policy::Saturate);
+        assert_eq!(saturate.to_i64(42, "bytes"), 42);
+        assert_eq!(saturate.to_i64(big, "bytes"), i64::MAX);
+
+        let wrap = U64Conversion::new(U64Policy::Wrap { modulo: MAX_MODULO });
+        assert_eq!(wrap.to_i64(42, "bytes"), 42);
+        assert_eq!(wrap.to_i64(big, "bytes"), (big - MAX_MODULO) as i64);
+        let wrap = U64Conversion::new(U64Policy::Wrap { modulo: 1000 });
+        assert_eq!(wrap.to_i64(1042, "bytes"), 42);
+
+        assert_eq!(U64Policy::Double.value_type(), ValueType::Double);
+        assert_eq!(U64Policy::default().value_type(), ValueType::Int64);
+        assert!(U64Policy::Wrap { modulo: MAX_MODULO }.validate().is_ok());
+        assert!(U64Policy::Wrap { modulo: 0 }.validate().is_err());
+        assert!(U64Policy::Wrap { modulo: MAX_MODULO + 1 }.validate().is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    borrow::Cow,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use opentelemetry_sdk::Resource;
+use rand::Rng;
+
+use super::GCPMetricsExporterConfig;
+
+pub(crate) const DEFAULT_UNIQUE_IDENTIFIER_KEY: &str = "opentelemetry_id";
+
+/// Value of the unique identifier label, see
+/// [`GCPMetricsExporterConfig::add_unique_identifier`].
+///
+/// A random id changes on every restart, which starts new time series each
+/// time. The other sources keep the series of a replica across restarts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UniqueIdentifierSource {
+    /// 8 random hex digits, generated when the exporter is built.
+    #[default]
+    Random,
+    /// `HOSTNAME` env var, or `/etc/hostname`.
+    Hostname,
+    /// `POD_NAME` env var, e.g. set with the Kubernetes downward API, or
+    /// `HOSTNAME` inside a Kubernetes pod.
+    PodName,
+    /// Attribute of the exported resource, e.g. `service.instance.id`.
+    /// The random id is used while the attribute is missing.
+    ResourceAttribute(String),
+    Value(String),
+}
+
+impl UniqueIdentifierSource {
+    /// Value known when the exporter is built, `None` for
+    /// [`UniqueIdentifierSource::ResourceAttribute`] or when the source is empty.
+    fn resolve(&self, env: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+        let value = match self {
+            Self::Random => Some(random_identifier()),
+            Self::Hostname => env("HOSTNAME").or_else(|| std::fs::read_to_string("/etc/hostname").ok()),
+            Self::PodName => env("POD_NAME").or_else(|| env("KUBERNETES_SERVICE_HOST").and_then(|_| env("HOSTNAME"))),
+            Self::ResourceAttribute(_) => None,
+            Self::Value(value) => Some(value.clone()),
+        };
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+fn random_identifier() -> String {
+    format!("{:08x}", rand::rng().random_range(0..u32::MAX))
+}
+
+/// Label key and value source of the unique identifier of an exporter.
+#[derive(Debug)]
+pub(crate) struct UniqueIdentifier {
+    pub(crate) key: String,
+    /// Resolved value, or the random fallback of a resource attribute.
+    value: String,
+    resource_attribute: Option<opentelemetry::Key>,
+    warned_missing: AtomicBool,
+}
+
+impl UniqueIdentifier {
+    /// `None` unless `add_unique_identifier` is set.
+    pub(crate) fn new(config: &GCPMetricsExporterConfig) -> Option<Self> {
+        if !config.add_unique_identifier {
+            return None;
+        }
+        let source = &config.unique_identifier_source;
+        Some(Self {
+            key: config.unique_identifier_key.clone(),
+            value: source
+                .resolve(&|name| std::env::var(name).ok())
+                .unwrap_or_else(random_identifier),
+            resource_attribute: match source {
+                UniqueIdentifierSource::ResourceAttribute(key) => Some(opentelemetry::Key::new(key.clone())),
+                _ => None,
+            },
+            warned_missing: AtomicBool::new(false),
+        })
+    }
+
+    /// Value of the label for the time series of `resource`.
+    pub(crate) fn value(&self, resource: &Resource) -> Cow<'_, str> {
+        let Some(attribute) = &self.resource_attribute else {
+            return Cow::Borrowed(&self.value);
+        };
+        match resource
+            .get(attribute)
+            .map(|value| value.to_string())
+            .filter(|value| !value.is_empty())
+        {
+            Some(value) => Cow::Owned(value),
+            None => {
+                if !self.warned_missing.swap(true, Ordering::Relaxed) {
+                    tracing::warn!(
+                        "GCPMetricsExporter: resource attribute {} of the unique identifier is missing, using {}",
+                        attribute,
+                        self.value
+                    );
+                }
+                Cow::Borrowed(&self.value)
+            }
+        }
+    }
+}
+
+/// The label key is not empty and the source has a value.
+pub(crate) fn validate_unique_identifier(config: &GCPMetricsExporterConfig) -> Result<(), String> {
+    validate_unique_identifier_with_env(config, &|name| std::env::var(name).ok())
+}
+
+fn validate_unique_identifier_with_env(
+    config: &GCPMetricsExporterConfig,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    if !config.add_unique_identifier {
+        return Ok(());
+    }
+    if config.unique_identifier_key.is_empty() {
+        return Err("unique_identifier_key must not be empty".to_string());
+    }
+    let source = &config.unique_identifier_source;
+    let empty = match source {
+        UniqueIdentifierSource::ResourceAttribute(key) => key.is_empty(),
+        _ => source.resolve(env).is_none(),
+    };
+    if empty {
+        return Err(format!("unique_identifier_source {:?} is empty", source));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::KeyValue;
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    fn config(source: UniqueIdentifierSource) -> GCPMetricsExporterConfig {
+        GCPMetricsExporterConfig {
+            add_unique_identifier: true,
+            unique_identifier_source: source,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_unique_identifier() {
+        let env = |name: &str| match name {
+            "HOSTNAME" => Some("checkout-7d9f\n".to_string()),
+            "KUBERNETES_SERVICE_HOST" => Some("10.0.0.1".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            UniqueIdentifierSource::Hostname.resolve(&env),
+            Some("checkout-7d9f".to_string())
+        );
+        assert_eq!(
+            UniqueIdentifierSource::PodName.resolve(&env),
+            Some("checkout-7d9f".to_string())
+        );
+        assert_eq!(UniqueIdentifierSource::PodName.resolve(&|_| None), None);
+        assert_eq!(UniqueIdentifierSource::Random.resolve(&|_| None).unwrap().len(), 8);
+
+        assert!(validate_unique_identifier_with_env(&config(UniqueIdentifierSource::PodName), &env).is_ok());
+        assert!(validate_unique_identifier_with_env(&config(UniqueIdentifierSource::PodName), &|_| None).is_err());
+        assert!(
+            validate_unique_identifier_with_env(&config(UniqueIdentifierSource::Value(" ".to_string())), &env).is_err()
+        );
+        assert!(
+            validate_unique_identifier_with_env(
+                &config(UniqueIdentifierSource::ResourceAttribute(String::new())),
+                &env
+            )
+            .is_err()
+        );
+        let empty_key = GCPMetricsExporterConfig {
+            unique_identifier_key: String::new(),
+            ..config(UniqueIdentifierSource::Random)
+        };
+        assert!(validate_unique_identifier_with_env(&empty_key, &env).is_err());
+    }
+
+    #[test]
+    fn test_unique_identifier_resource_attribute() {
+        let unique_identifier = UniqueIdentifier::new(&config(UniqueIdentifierSource::ResourceAttribute(
+            "service.instance.id".to_string(),
+        )))
+        .unwrap();
+        let resource = Resource::builder_empty()
+            .with_attributes(vec![KeyValue::new("service.instance.id", "checkout-1")])
+            .build();
+        assert_eq!(unique_identifier.value(&resource), "checkout-1");
+        let fallback = unique_identifier.value(&Resource::builder_empty().build());
+        assert_eq!(fallback.len(), 8);
+    }
+}
@@ -1,78 +1,68 @@
 use opentelemetry::KeyValue;
 use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
 
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Duration};
 
-pub(crate) fn get_data_points_attributes_keys(data: &AggregatedMetrics) -> HashSet<String> {
-    let attributes_keys: Vec<String> = match data {
+/// Cloud Monitoring rejects points whose end time is older than 25 hours.
+pub(crate) const MAX_POINT_AGE: Duration = Duration::from_secs(25 * 60 * 60);
+
+pub(crate) fn get_data_points_attributes_keys(data: &AggregatedMetrics) -> HashSet<&str> {
+    match data {
         AggregatedMetrics::F64(v) => match v {
             MetricData::Histogram(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
             MetricData::ExponentialHistogram(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
             MetricData::Sum(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
             MetricData::Gauge(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
         },
         AggregatedMetrics::I64(v) => match v {
             MetricData::Histogram(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
             MetricData::ExponentialHistogram(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
             MetricData::Sum(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
             MetricData::Gauge(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
         },
         AggregatedMetrics::U64(v) => match v {
             MetricData::Histogram(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
             MetricData::ExponentialHistogram(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
             MetricData::Sum(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
             MetricData::Gauge(m) => m
                 .data_points()
-                .map(|point| point.attributes().map(kv_map_k))
-                .flatten()
+                .flat_map(|point| point.attributes().map(kv_map_k))
                 .collect(),
         },
-    };
-    HashSet::from_iter(attributes_keys.into_iter())
+    }
 }
 
 use unicode_segmentation::UnicodeSegmentation;
@@ -88,7 +78,7 @@ pub(crate) fn normalize_label_key(s: &str) -> String {
         return s.to_string();
     }
     let s = sanitize_string(s);
-    if s.chars().next().map_or(false, |c| c.is_digit(10)) {
+    if s.chars().next().is_some_and(|c| c.is_ascii_digit()) {
         return format!("key_{}", s);
     }
     s
@@ -96,45 +86,30 @@ pub(crate) fn normalize_label_key(s: &str) -> String {
 
 // Converts anything that is not a letter or digit to an underscore
 fn sanitize_string(s: &str) -> String {
-    s.graphemes(true)
-        .map(|g| {
-            if g.chars().all(|c| c.is_alphanumeric()) {
-                g.to_string()
-            } else {
-                "_".to_string()
-            }
-        })
-        .collect::<String>()
+    // every ascii char but "\r\n" is a grapheme, skip the segmentation
+    if s.is_ascii() && !s.contains('\r') {
+        return s
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+    }
+    let mut sanitized = String::with_capacity(s.len());
+    for g in s.graphemes(true) {
+        if g.chars().all(|c| c.is_alphanumeric()) {
+            sanitized.push_str(g);
+        } else {
+            sanitized.push('_');
+        }
+    }
+    sanitized
 }
 
 pub(crate) fn kv_map_normalize_k_v(kv: &KeyValue) -> (String, String) {
-    (normalize_label_key(&kv.key.to_string()), kv.value.to_string())
-}
-
-pub(crate) fn kv_map_k(kv: &KeyValue) -> String {
-    kv.key.to_string()
+    (normalize_label_key(kv.key.as_ref()), kv.value.to_string())
 }
 
-pub(crate) async fn get_project_id() -> Result<String, String> {
-    if let Ok(project_id) = std::env::var("GOOGLE_CLOUD_PROJECT") {
-        return Ok(project_id);
-    }
-
-    let client = reqwest::Client::new();
-    let url = "http://metadata.google.internal/computeMetadata/v1/project/project-id";
-
-    let response = client.get(url).header("Metadata-Flavor", "Google").send().await;
-
-    match response {
-        Ok(res) => {
-            if res.status().is_success() {
-                Ok(res.text().await.map_err(|e| e.to_string())?)
-            } else {
-                Err(format!("Metadata server returned error: {}", res.status()))
-            }
-        }
-        Err(e) => Err(format!("Error querying metadata server: {}", e)),
-    }
+pub(crate) fn kv_map_k(kv: &KeyValue) -> &str {
+    kv.key.as_str()
 }
 
 #[cfg(test)]
@@ -149,5 +124,6 @@ mod tests {
         assert_eq!(normalize_label_key("key!321"), "key_321");
         assert_eq!(normalize_label_key("hyphens-dots.slashes/"), "hyphens_dots_slashes_");
         assert_eq!(normalize_label_key("non_letters_:£¢$∞"), "non_letters______");
+        assert_eq!(normalize_label_key("line\r\nbreak\r"), "line_break_");
     }
 }
@@ -1,9 +1,55 @@
 #![allow(unexpected_cfgs)]
 mod exporter;
 
+pub use exporter::CredentialsConfig;
+pub use exporter::DryRunConfig;
+pub use exporter::DryRunFormat;
+pub use exporter::DryRunOutput;
+pub use exporter::ExportErrorDetails;
+pub use exporter::ExportErrorHandler;
+pub use exporter::ExportOperation;
+pub use exporter::ExportOutcome;
+pub use exporter::ExportStats;
+pub use exporter::ExportStatsHandle;
+pub use exporter::GCPMetricsExportError;
 pub use exporter::GCPMetricsExporter;
+pub use exporter::GCPMetricsExporterBuilder;
 pub use exporter::GCPMetricsExporterConfig;
+pub use exporter::GCPMetricsExporterInitError;
+pub use exporter::GcpResourceDetectorMapper;
+pub use exporter::GenericNodeMapper;
+pub use exporter::GenericTaskMapper;
+pub use exporter::LabelDescriptorConfig;
+#[cfg(feature = "cli")]
+pub use exporter::MetricDescriptorAdmin;
+#[cfg(feature = "cli")]
+pub use exporter::MetricDescriptorDiff;
+pub use exporter::MetricSchema;
+pub use exporter::MetricSchemaEntry;
+pub use exporter::MetricSchemaLabel;
 pub use exporter::MonitoredResourceDataConfig;
+pub use exporter::MonitoredResourceMapper;
+pub use exporter::MonitoredResourceResolver;
+pub use exporter::MonitoredResourceResolverFn;
+pub use exporter::ProjectIdDetectionConfig;
+pub use exporter::ProjectIdSource;
+pub use exporter::ProjectRouterFn;
+pub use exporter::ProjectRouting;
+pub use exporter::PrometheusConfig;
+pub use exporter::PrometheusTargetMapper;
+#[cfg(feature = "spool")]
+pub use exporter::SpoolConfig;
+pub use exporter::U64Policy;
+pub use exporter::UndeclaredPolicy;
+pub use exporter::UniqueIdentifierSource;
+#[cfg(feature = "cli")]
+pub use exporter::diff_metric_descriptors;
+#[cfg(feature = "cli")]
+pub use exporter::read_metric_descriptors;
+#[cfg(feature = "cli")]
+pub use exporter::validate_prune_prefix;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 #[cfg(test)]
 mod tests;
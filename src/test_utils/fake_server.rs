@@ -0,0 +1,237 @@
+use google_cloud_gax::error::rpc::Code;
+use google_cloud_monitoring_v3::stub::MetricService as _;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use super::MockMetricService;
+
+/// Minimal HTTP/JSON stand-in of the Cloud Monitoring API on `127.0.0.1`.
+///
+/// Serves the `CreateMetricDescriptor`, `ListMetricDescriptors`,
+/// `DeleteMetricDescriptor` and `CreateTimeSeries` REST routes with a
+/// [`MockMetricService`], so recorded requests, injected errors and
+/// latency work the same as with the mock. Point an exporter at it with
+/// `endpoint`, `insecure` and [`crate::CredentialsConfig::Anonymous`].
+/// The server stops when dropped.
+#[derive(Debug)]
+pub struct FakeMonitoringServer {
+    endpoint: String,
+    mock_service: MockMetricService,
+    task: JoinHandle<()>,
+}
+
+impl FakeMonitoringServer {
+    /// Listens on a free port, see [`FakeMonitoringServer::endpoint`].
+    pub async fn start(mock_service: MockMetricService) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let task = tokio::spawn({
+            let mock_service = mock_service.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle_connection(stream, mock_service.clone()));
+                }
+            }
+        });
+        Ok(Self {
+            endpoint,
+            mock_service,
+            task,
+        })
+    }
+
+    /// `http://127.0.0.1:{port}`
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn mock_service(&self) -> &MockMetricService {
+        &self.mock_service
+    }
+}
+
+impl Drop for FakeMonitoringServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serves one request and closes the connection.
+async fn handle_connection(stream: TcpStream, mock_service: MockMetricService) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = percent_decode(path);
+    let (status, response) = match route(method, &path, query, &body, &mock_service).await {
+        Ok(response) => (200, response),
+        Err((code, message)) => error_response(code, &message),
+    };
+
+    let mut stream = reader.into_inner();
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                status,
+                reason_phrase(status),
+                response.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+    stream.write_all(&response).await?;
+    stream.shutdown().await
+}
+
+async fn route(
+    method: &str,
+    path: &str,
+    query: &str,
+    body: &[u8],
+    mock_service: &MockMetricService,
+) -> Result<Vec<u8>, (Code, String)> {
+    let options = google_cloud_gax::options::RequestOptions::default();
+    let invalid = |err: serde_json::Error| (Code::InvalidArgument, err.to_string());
+    let segments = path.trim_start_matches('/').split('/').collect::<Vec<_>>();
+    match (method, segments.as_slice()) {
+        ("POST", ["v3", "projects", project, "metricDescriptors"]) => {
+            let descriptor =
+                serde_json::from_slice::<google_cloud_api::model::MetricDescriptor>(body).map_err(invalid)?;
+            let req = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
+                .set_name(format!("projects/{}", project))
+                .set_metric_descriptor(descriptor);
+            let resp = mock_service
+                .create_metric_descriptor(req, options)
+                .await
+                .map_err(rpc_error)?;
+            serde_json::to_vec(resp.body()).map_err(invalid)
+        }
+        ("GET", ["v3", "projects", project, "metricDescriptors"]) => {
+            let mut req = google_cloud_monitoring_v3::model::ListMetricDescriptorsRequest::new()
+                .set_name(format!("projects/{}", project));
+            for (key, value) in query.split('&').filter_map(|param| param.split_once('=')) {
+                match key {
+                    "filter" => req.filter = percent_decode(&value.replace('+', " ")),
+                    "pageToken" => req.page_token = percent_decode(value),
+                    _ => {}
+                }
+            }
+            let resp = mock_service
+                .list_metric_descriptors(req, options)
+                .await
+                .map_err(rpc_error)?;
+            serde_json::to_vec(resp.body()).map_err(invalid)
+        }
+        ("DELETE", ["v3", "projects", project, "metricDescriptors", metric_type @ ..]) => {
+            let req = google_cloud_monitoring_v3::model::DeleteMetricDescriptorRequest::new().set_name(format!(
+                "projects/{}/metricDescriptors/{}",
+                project,
+                metric_type.join("/")
+            ));
+            mock_service
+                .delete_metric_descriptor(req, options)
+                .await
+                .map_err(rpc_error)?;
+            Ok(b"{}".to_vec())
+        }
+        ("POST", ["v3", "projects", project, "timeSeries"]) => {
+            let req = serde_json::from_slice::<google_cloud_monitoring_v3::model::CreateTimeSeriesRequest>(body)
+                .map_err(invalid)?
+                .set_name(format!("projects/{}", project));
+            mock_service.create_time_series(req, options).await.map_err(rpc_error)?;
+            Ok(b"{}".to_vec())
+        }
+        _ => Err((Code::NotFound, format!("{} {} is not implemented", method, path))),
+    }
+}
+
+/// Decodes the `%XX` escapes of a URL path or query value.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn rpc_error(err: google_cloud_gax::error::Error) -> (Code, String) {
+    match err.status() {
+        Some(status) => (status.code, status.message.clone()),
+        None => (Code::Internal, err.to_string()),
+    }
+}
+
+/// Error body in the format the REST transport of `google-cloud-gax` parses.
+fn error_response(code: Code, message: &str) -> (u16, Vec<u8>) {
+    let status = match code {
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => 400,
+        Code::Unauthenticated => 401,
+        Code::PermissionDenied => 403,
+        Code::NotFound => 404,
+        Code::AlreadyExists | Code::Aborted => 409,
+        Code::ResourceExhausted => 429,
+        Code::Cancelled => 499,
+        Code::Unimplemented => 501,
+        Code::Unavailable => 503,
+        Code::DeadlineExceeded => 504,
+        _ => 500,
+    };
+    let body = serde_json::json!({
+        "error": { "code": status, "message": message, "status": code.name() }
+    });
+    (status, body.to_string().into_bytes())
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        429 => "Too Many Requests",
+        499 => "Client Closed Request",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Internal Server Error",
+    }
+}
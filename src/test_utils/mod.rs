@@ -0,0 +1,438 @@
+//! In-memory [`MockMetricService`] for asserting on what an exporter would
+//! send to Cloud Monitoring, available with the `test-utils` feature.
+//!
+//! ```ignore
+//! let mock_service = MockMetricService::new();
+//! let metrics_provider = init_metrics(mock_service.clone(), vec![KeyValue::new("service.name", "my-service")]);
+//! // record some metrics
+//! metrics_provider.force_flush()?;
+//! let requests = mock_service.create_time_series_requests().await;
+//! ```
+//!
+//! [`FakeMonitoringServer`] serves the same mock over HTTP, to test the
+//! exporter built by [`crate::GCPMetricsExporter::init`] end to end.
+mod fake_server;
+
+pub use fake_server::FakeMonitoringServer;
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use google_cloud_gax::error::rpc::Code;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::metrics::{
+    MeterProviderBuilder, SdkMeterProvider, periodic_reader_with_async_runtime::PeriodicReader,
+};
+use tokio::sync::Mutex;
+
+/// `MetricService` RPCs the mock records and can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockMethod {
+    CreateMetricDescriptor,
+    CreateTimeSeries,
+    DeleteMetricDescriptor,
+    GetMonitoredResourceDescriptor,
+    ListMetricDescriptors,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    /// JSON encoded requests of the successful calls.
+    calls: HashMap<MockMethod, Vec<String>>,
+    /// Returned by every call until cleared.
+    errors: HashMap<MockMethod, Code>,
+    /// Returned once each, in order, before `errors`.
+    scripted_errors: HashMap<MockMethod, VecDeque<Code>>,
+    latency: Option<Duration>,
+    /// Served by `GetMonitoredResourceDescriptor`, by type.
+    monitored_resource_descriptors: HashMap<String, google_cloud_api::model::MonitoredResourceDescriptor>,
+    /// Created or added metric descriptors by name, served by
+    /// `ListMetricDescriptors` and removed by `DeleteMetricDescriptor`.
+    metric_descriptors: BTreeMap<String, google_cloud_api::model::MetricDescriptor>,
+}
+
+/// [`google_cloud_monitoring_v3::stub::MetricService`] that records
+/// `CreateMetricDescriptor` and `CreateTimeSeries` calls instead of sending them,
+/// and serves the monitored resource descriptors added with
+/// [`MockMetricService::add_monitored_resource_descriptor`]. The created
+/// metric descriptors, and the ones added with
+/// [`MockMetricService::add_metric_descriptor`], can be listed and deleted.
+///
+/// Clones share the recorded calls and the injected errors, keep one clone to
+/// inspect the calls after the exporter was moved into a reader.
+/// Other RPCs fail with `Unimplemented`.
+#[derive(Default, Debug, Clone)]
+pub struct MockMetricService {
+    state: Arc<Mutex<MockState>>,
+}
+
+async fn unimplemented_stub<T: Send>() -> google_cloud_gax::Result<google_cloud_gax::response::Response<T>> {
+    Err(google_cloud_gax::error::Error::service(
+        google_cloud_gax::error::rpc::Status::default()
+            .set_code(Code::Unimplemented)
+            .set_message("not implemented by MockMetricService"),
+    ))
+}
+
+impl MockMetricService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes every following `method` call fail with `code` (or succeed with `None`).
+    /// Failed calls are not recorded.
+    pub async fn set_error(&self, method: MockMethod, code: Option<Code>) {
+        let mut state = self.state.lock().await;
+        match code {
+            Some(code) => state.errors.insert(method, code),
+            None => state.errors.remove(&method),
+        };
+    }
+
+    /// Makes every following `CreateTimeSeries` call fail with `code` (or succeed with `None`).
+    /// Failed calls are not recorded.
+    pub async fn set_create_time_series_error(&self, code: Option<Code>) {
+        self.set_error(MockMethod::CreateTimeSeries, code).await;
+    }
+
+    /// Makes the next `method` calls fail with `codes`, one call per code,
+    /// before falling back to the error set with [`MockMetricService::set_error`].
+    pub async fn push_errors(&self, method: MockMethod, codes: impl IntoIterator<Item = Code>) {
+        self.state
+            .lock()
+            .await
+            .scripted_errors
+            .entry(method)
+            .or_default()
+            .extend(codes);
+    }
+
+    /// Delays every following call by `latency` (or not at all with `None`).
+    pub async fn set_latency(&self, latency: Option<Duration>) {
+        self.state.lock().await.latency = latency;
+    }
+
+    /// Served by `GetMonitoredResourceDescriptor`, other types are `NotFound`.
+    pub async fn add_monitored_resource_descriptor(
+        &self,
+        descriptor: google_cloud_api::model::MonitoredResourceDescriptor,
+    ) {
+        self.state
+            .lock()
+            .await
+            .monitored_resource_descriptors
+            .insert(descriptor.r#type.clone(), descriptor);
+    }
+
+    /// Served by `ListMetricDescriptors` for `project_id` until deleted.
+    pub async fn add_metric_descriptor(&self, project_id: &str, descriptor: google_cloud_api::model::MetricDescriptor) {
+        let name = format!("projects/{}/metricDescriptors/{}", project_id, descriptor.r#type);
+        self.state
+            .lock()
+            .await
+            .metric_descriptors
+            .insert(name.clone(), descriptor.set_name(name));
+    }
+
+    /// Forgets the recorded calls, injected errors, latency and monitored
+    /// resource and metric descriptors.
+    pub async fn reset(&self) {
+        *self.state.lock().await = MockState::default();
+    }
+
+    pub async fn create_metric_descriptor_requests(
+        &self,
+    ) -> Vec<google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest> {
+        self.requests(MockMethod::CreateMetricDescriptor).await
+    }
+
+    pub async fn create_time_series_requests(&self) -> Vec<google_cloud_monitoring_v3::model::CreateTimeSeriesRequest> {
+        self.requests(MockMethod::CreateTimeSeries).await
+    }
+
+    pub async fn delete_metric_descriptor_requests(
+        &self,
+    ) -> Vec<google_cloud_monitoring_v3::model::DeleteMetricDescriptorRequest> {
+        self.requests(MockMethod::DeleteMetricDescriptor).await
+    }
+
+    /// Like [`MockMetricService::create_metric_descriptor_requests`], panics if there are none.
+    pub async fn expect_create_metric_descriptor(
+        &self,
+    ) -> Vec<google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest> {
+        let requests = self.create_metric_descriptor_requests().await;
+        assert!(!requests.is_empty(), "no CreateMetricDescriptor call recorded");
+        requests
+    }
+
+    /// Like [`MockMetricService::create_time_series_requests`], panics if there are none.
+    pub async fn expect_create_time_series(&self) -> Vec<google_cloud_monitoring_v3::model::CreateTimeSeriesRequest> {
+        let requests = self.create_time_series_requests().await;
+        assert!(!requests.is_empty(), "no CreateTimeSeries call recorded");
+        requests
+    }
+
+    async fn requests<T: serde::de::DeserializeOwned>(&self, method: MockMethod) -> Vec<T> {
+        let state = self.state.lock().await;
+        state
+            .calls
+            .get(&method)
+            .into_iter()
+            .flatten()
+            .map(|message| serde_json::from_str::<T>(message).unwrap())
+            .collect()
+    }
+
+    /// Waits for the injected latency, then fails with the injected error or records `req`.
+    async fn call<T: serde::Serialize>(&self, method: MockMethod, req: &T) -> google_cloud_gax::Result<()> {
+        let (latency, error) = {
+            let mut state = self.state.lock().await;
+            let error = state
+                .scripted_errors
+                .get_mut(&method)
+                .and_then(|codes| codes.pop_front())
+                .or_else(|| state.errors.get(&method).copied());
+            (state.latency, error)
+        };
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+        if let Some(code) = error {
+            return Err(google_cloud_gax::error::Error::service(
+                google_cloud_gax::error::rpc::Status::default()
+                    .set_code(code)
+                    .set_message("mock error"),
+            ));
+        }
+        self.state
+            .lock()
+            .await
+            .calls
+            .entry(method)
+            .or_default()
+            .push(serde_json::to_string(req).unwrap());
+        Ok(())
+    }
+}
+
+impl google_cloud_monitoring_v3::stub::MetricService for MockMetricService {
+    /// Implements [google_cloud_monitoring_v3::client::MetricService::list_monitored_resource_descriptors].
+    fn list_monitored_resource_descriptors(
+        &self,
+        _req: google_cloud_monitoring_v3::model::ListMonitoredResourceDescriptorsRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> impl std::future::Future<
+        Output = google_cloud_monitoring_v3::Result<
+            google_cloud_gax::response::Response<
+                google_cloud_monitoring_v3::model::ListMonitoredResourceDescriptorsResponse,
+            >,
+        >,
+    > + Send {
+        unimplemented_stub()
+    }
+
+    /// Implements [google_cloud_monitoring_v3::client::MetricService::get_monitored_resource_descriptor].
+    fn get_monitored_resource_descriptor(
+        &self,
+        req: google_cloud_monitoring_v3::model::GetMonitoredResourceDescriptorRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> impl std::future::Future<
+        Output = google_cloud_monitoring_v3::Result<
+            google_cloud_gax::response::Response<google_cloud_api::model::MonitoredResourceDescriptor>,
+        >,
+    > + Send {
+        let mock = self.clone();
+        Box::pin(async move {
+            mock.call(MockMethod::GetMonitoredResourceDescriptor, &req).await?;
+            let r#type = req.name.rsplit('/').next().unwrap_or_default();
+            match mock.state.lock().await.monitored_resource_descriptors.get(r#type) {
+                Some(descriptor) => Ok(google_cloud_gax::response::Response::from(descriptor.clone())),
+                None => Err(google_cloud_gax::error::Error::service(
+                    google_cloud_gax::error::rpc::Status::default()
+                        .set_code(Code::NotFound)
+                        .set_message(format!("unknown monitored resource type {}", r#type)),
+                )),
+            }
+        })
+    }
+
+    /// Implements [google_cloud_monitoring_v3::client::MetricService::list_metric_descriptors].
+    /// Only supports the `metric.type = starts_with("...")` filter, returns a single page.
+    fn list_metric_descriptors(
+        &self,
+        req: google_cloud_monitoring_v3::model::ListMetricDescriptorsRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> impl std::future::Future<
+        Output = google_cloud_monitoring_v3::Result<
+            google_cloud_gax::response::Response<google_cloud_monitoring_v3::model::ListMetricDescriptorsResponse>,
+        >,
+    > + Send {
+        let mock = self.clone();
+        Box::pin(async move {
+            mock.call(MockMethod::ListMetricDescriptors, &req).await?;
+            let prefix = req
+                .filter
+                .split_once("starts_with(\"")
+                .and_then(|(_, rest)| rest.split_once('"'))
+                .map(|(prefix, _)| prefix.to_string())
+                .unwrap_or_default();
+            let parent = format!("{}/metricDescriptors/", req.name);
+            let descriptors = mock
+                .state
+                .lock()
+                .await
+                .metric_descriptors
+                .iter()
+                .filter(|(name, descriptor)| name.starts_with(&parent) && descriptor.r#type.starts_with(&prefix))
+                .map(|(_, descriptor)| descriptor.clone())
+                .collect::<Vec<_>>();
+            Ok(google_cloud_gax::response::Response::from(
+                google_cloud_monitoring_v3::model::ListMetricDescriptorsResponse::new()
+                    .set_metric_descriptors(descriptors),
+            ))
+        })
+    }
+
+    /// Implements [google_cloud_monitoring_v3::client::MetricService::get_metric_descriptor].
+    fn get_metric_descriptor(
+        &self,
+        _req: google_cloud_monitoring_v3::model::GetMetricDescriptorRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> impl std::future::Future<
+        Output = google_cloud_monitoring_v3::Result<
+            google_cloud_gax::response::Response<google_cloud_api::model::MetricDescriptor>,
+        >,
+    > + Send {
+        unimplemented_stub()
+    }
+
+    /// Implements [google_cloud_monitoring_v3::client::MetricService::create_metric_descriptor].
+    fn create_metric_descriptor(
+        &self,
+        req: google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> impl std::future::Future<
+        Output = google_cloud_monitoring_v3::Result<
+            google_cloud_gax::response::Response<google_cloud_api::model::MetricDescriptor>,
+        >,
+    > + Send {
+        let mock = self.clone();
+        Box::pin(async move {
+            mock.call(MockMethod::CreateMetricDescriptor, &req).await?;
+            let descriptor = req.metric_descriptor.unwrap_or_default();
+            let name = format!("{}/metricDescriptors/{}", req.name, descriptor.r#type);
+            mock.state
+                .lock()
+                .await
+                .metric_descriptors
+                .insert(name.clone(), descriptor.clone().set_name(name));
+            Ok(google_cloud_gax::response::Response::from(descriptor))
+        })
+    }
+
+    /// Implements [google_cloud_monitoring_v3::client::MetricService::delete_metric_descriptor].
+    fn delete_metric_descriptor(
+        &self,
+        req: google_cloud_monitoring_v3::model::DeleteMetricDescriptorRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> impl std::future::Future<Output = google_cloud_monitoring_v3::Result<google_cloud_gax::response::Response<()>>> + Send
+    {
+        let mock = self.clone();
+        Box::pin(async move {
+            mock.call(MockMethod::DeleteMetricDescriptor, &req).await?;
+            match mock.state.lock().await.metric_descriptors.remove(&req.name) {
+                Some(_) => Ok(google_cloud_gax::response::Response::from(())),
+                None => Err(google_cloud_gax::error::Error::service(
+                    google_cloud_gax::error::rpc::Status::default()
+                        .set_code(Code::NotFound)
+                        .set_message(format!("unknown metric descriptor {}", req.name)),
+                )),
+            }
+        })
+    }
+
+    /// Implements [google_cloud_monitoring_v3::client::MetricService::list_time_series].
+    fn list_time_series(
+        &self,
+        _req: google_cloud_monitoring_v3::model::ListTimeSeriesRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> impl std::future::Future<
+        Output = google_cloud_monitoring_v3::Result<
+            google_cloud_gax::response::Response<google_cloud_monitoring_v3::model::ListTimeSeriesResponse>,
+        >,
+    > + Send {
+        unimplemented_stub()
+    }
+
+    /// Implements [google_cloud_monitoring_v3::client::MetricService::create_time_series].
+    fn create_time_series(
+        &self,
+        req: google_cloud_monitoring_v3::model::CreateTimeSeriesRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> impl std::future::Future<Output = google_cloud_monitoring_v3::Result<google_cloud_gax::response::Response<()>>> + Send
+    {
+        let mock = self.clone();
+        Box::pin(async move {
+            mock.call(MockMethod::CreateTimeSeries, &req).await?;
+            Ok(google_cloud_gax::response::Response::from(()))
+        })
+    }
+
+    /// Implements [google_cloud_monitoring_v3::client::MetricService::create_service_time_series].
+    fn create_service_time_series(
+        &self,
+        _req: google_cloud_monitoring_v3::model::CreateTimeSeriesRequest,
+        _options: google_cloud_gax::options::RequestOptions,
+    ) -> impl std::future::Future<Output = google_cloud_monitoring_v3::Result<google_cloud_gax::response::Response<()>>> + Send
+    {
+        unimplemented_stub()
+    }
+}
+
+/// Exporter sending to `mock_service` with the default config and project id `fake_project_id`.
+pub fn init_metrics_exporter<T: google_cloud_monitoring_v3::stub::MetricService + 'static>(
+    mock_service: T,
+) -> crate::GCPMetricsExporter {
+    init_metrics_exporter_with_config(mock_service, crate::GCPMetricsExporterConfig::default())
+}
+
+/// Exporter sending to `mock_service` with project id `fake_project_id`.
+///
+/// Panics if [`crate::GCPMetricsExporterBuilder::build`] would reject
+/// `config` with a metric service.
+pub fn init_metrics_exporter_with_config<T: google_cloud_monitoring_v3::stub::MetricService + 'static>(
+    mock_service: T,
+    config: crate::GCPMetricsExporterConfig,
+) -> crate::GCPMetricsExporter {
+    if let Err(err) =
+        crate::exporter::validate_config(&config).and_then(|_| crate::exporter::validate_metric_service_config(&config))
+    {
+        panic!("{}", err);
+    }
+    let client = google_cloud_monitoring_v3::client::MetricService::from_stub(mock_service);
+    crate::GCPMetricsExporter::new(client, "fake_project_id".to_string(), config)
+}
+
+/// Meter provider with `res_attributes` as resource, exporting to `mock_service` on `force_flush`.
+pub fn init_metrics<T: google_cloud_monitoring_v3::stub::MetricService + 'static>(
+    mock_service: T,
+    res_attributes: Vec<opentelemetry::KeyValue>,
+) -> SdkMeterProvider {
+    let builder =
+        SdkMeterProvider::builder().with_resource(Resource::builder_empty().with_attributes(res_attributes).build());
+    init_metrics_with_config(mock_service, crate::GCPMetricsExporterConfig::default(), builder).0
+}
+
+/// Meter provider of `builder`, e.g. with a resource or views, exporting to
+/// `mock_service` with `config` on `force_flush`, and the stats of the exporter.
+pub fn init_metrics_with_config<T: google_cloud_monitoring_v3::stub::MetricService + 'static>(
+    mock_service: T,
+    config: crate::GCPMetricsExporterConfig,
+    builder: MeterProviderBuilder,
+) -> (SdkMeterProvider, crate::ExportStatsHandle) {
+    let exporter = init_metrics_exporter_with_config(mock_service, config);
+    let stats = exporter.stats_handle();
+    let reader = PeriodicReader::builder(exporter, opentelemetry_sdk::runtime::Tokio).build();
+    (builder.with_reader(reader).build(), stats)
+}
@@ -1,2 +1,21 @@
+mod test_builder;
 mod test_cloud_monitoring;
+mod test_cumulative;
+#[cfg(feature = "cli")]
+mod test_descriptor_admin;
+mod test_dry_run;
+mod test_export_error;
+mod test_fake_server;
+mod test_interval;
+mod test_mock_metric_service;
+mod test_monitored_resource;
+mod test_prometheus;
+mod test_routing;
+mod test_runtime;
+mod test_schema;
+mod test_self_metrics;
+#[cfg(feature = "spool")]
+mod test_spool;
+mod test_stats;
+mod test_u64_policy;
 mod test_utils;
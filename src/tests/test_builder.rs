@@ -0,0 +1,251 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{
+        CredentialsConfig, DryRunConfig, GCPMetricsExporter, GCPMetricsExporterBuilder, GCPMetricsExporterInitError,
+        LabelDescriptorConfig, ProjectIdSource, UniqueIdentifierSource,
+    };
+
+    use google_cloud_api::model::{LabelDescriptor, label_descriptor::ValueType};
+    use google_cloud_gax::error::rpc::Code;
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{SdkMeterProvider, periodic_reader_with_async_runtime::PeriodicReader};
+    use opentelemetry_sdk::runtime;
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::collections::HashMap;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_with_metric_service() {
+        let mock_service = MockMetricService::new();
+        let exporter = GCPMetricsExporter::builder()
+            .with_metric_service(google_cloud_monitoring_v3::client::MetricService::from_stub(
+                mock_service.clone(),
+            ))
+            .with_project_id("my-project")
+            .with_prefix("custom.googleapis.com/my_service")
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(exporter.project_id(), "my-project");
+        assert_eq!(exporter.project_id_source(), ProjectIdSource::Config);
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+        mycounter.add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let descriptors = mock_service.expect_create_metric_descriptor().await;
+        assert_eq!(descriptors[0].name, "projects/my-project");
+        assert_eq!(
+            descriptors[0].metric_descriptor.as_ref().unwrap().r#type,
+            "custom.googleapis.com/my_service/mycounter"
+        );
+        let time_series = mock_service.expect_create_time_series().await;
+        assert_eq!(time_series[0].name, "projects/my-project");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_skip_metric_descriptor_creation() {
+        let mock_service = MockMetricService::new();
+        // the exporter must not need the metricDescriptors.create permission
+        mock_service
+            .set_error(MockMethod::CreateMetricDescriptor, Some(Code::PermissionDenied))
+            .await;
+        let exporter = GCPMetricsExporter::builder()
+            .with_metric_service(google_cloud_monitoring_v3::client::MetricService::from_stub(
+                mock_service.clone(),
+            ))
+            .with_project_id("my-project")
+            .with_skip_metric_descriptor_creation(true)
+            .build()
+            .await
+            .unwrap();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter.u64_counter("mycounter").build().add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        assert!(mock_service.create_metric_descriptor_requests().await.is_empty());
+        let time_series = mock_service.expect_create_time_series().await;
+        let metric = time_series[0].time_series[0].metric.as_ref().unwrap();
+        assert_eq!(metric.r#type, "workload.googleapis.com/mycounter");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_label_descriptors() {
+        let mock_service = MockMetricService::new();
+        let exporter = GCPMetricsExporter::builder()
+            .with_metric_service(google_cloud_monitoring_v3::client::MetricService::from_stub(
+                mock_service.clone(),
+            ))
+            .with_project_id("my-project")
+            .with_unique_identifier(true)
+            .with_label_descriptor(LabelDescriptorConfig {
+                key: "http.status_code".to_string(),
+                description: "HTTP status code.".to_string(),
+                value_type: ValueType::Int64,
+                ..Default::default()
+            })
+            .with_label_descriptor(LabelDescriptorConfig {
+                key: "opentelemetry_id".to_string(),
+                description: "Replica writing the time series.".to_string(),
+                ..Default::default()
+            })
+            .build()
+            .await
+            .unwrap();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter
+            .u64_counter("mycounter")
+            .build()
+            .add(1, &[KeyValue::new("http.status_code", 200)]);
+        metrics_provider.force_flush().unwrap();
+
+        let descriptors = mock_service.expect_create_metric_descriptor().await;
+        assert_eq!(
+            descriptors[0].metric_descriptor.as_ref().unwrap().labels,
+            vec![
+                LabelDescriptor::new()
+                    .set_key("http_status_code")
+                    .set_description("HTTP status code.")
+                    .set_value_type(ValueType::Int64),
+                LabelDescriptor::new()
+                    .set_key("opentelemetry_id")
+                    .set_description("Replica writing the time series."),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_unique_identifier() {
+        let mock_service = MockMetricService::new();
+        let exporter = GCPMetricsExporter::builder()
+            .with_metric_service(google_cloud_monitoring_v3::client::MetricService::from_stub(
+                mock_service.clone(),
+            ))
+            .with_project_id("my-project")
+            .with_unique_identifier(true)
+            .with_unique_identifier_key("replica")
+            .with_unique_identifier_source(UniqueIdentifierSource::Value("checkout-1".to_string()))
+            .build()
+            .await
+            .unwrap();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter.u64_counter("mycounter").build().add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let descriptors = mock_service.expect_create_metric_descriptor().await;
+        assert_eq!(
+            descriptors[0].metric_descriptor.as_ref().unwrap().labels[0].key,
+            "replica"
+        );
+        let time_series = mock_service.expect_create_time_series().await;
+        let metric = time_series[0].time_series[0].metric.as_ref().unwrap();
+        assert_eq!(
+            metric.labels,
+            HashMap::from([("replica".to_string(), "checkout-1".to_string())])
+        );
+
+        let res = GCPMetricsExporter::builder()
+            .with_metric_service(google_cloud_monitoring_v3::client::MetricService::from_stub(
+                MockMetricService::new(),
+            ))
+            .with_project_id("my-project")
+            .with_unique_identifier(true)
+            .with_unique_identifier_source(UniqueIdentifierSource::Value(String::new()))
+            .build()
+            .await;
+        assert!(matches!(res, Err(GCPMetricsExporterInitError::InvalidConfig(_))));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_invalid_config() {
+        let res = GCPMetricsExporter::builder()
+            .with_metric_service(google_cloud_monitoring_v3::client::MetricService::from_stub(
+                MockMetricService::new(),
+            ))
+            .with_project_id("my-project")
+            .with_prefix("")
+            .build()
+            .await;
+        assert!(matches!(res, Err(GCPMetricsExporterInitError::InvalidConfig(_))));
+    }
+
+    /// Error of a builder with a metric service and the options of `builder`.
+    async fn build_with_metric_service(builder: GCPMetricsExporterBuilder) -> GCPMetricsExporterInitError {
+        builder
+            .with_metric_service(google_cloud_monitoring_v3::client::MetricService::from_stub(
+                MockMetricService::new(),
+            ))
+            .with_project_id("my-project")
+            .build()
+            .await
+            .unwrap_err()
+    }
+
+    fn assert_ignored_option(err: GCPMetricsExporterInitError, option: &str) {
+        match err {
+            GCPMetricsExporterInitError::InvalidConfig(msg) => {
+                assert_eq!(msg, format!("{} can not be used with a metric service", option))
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_metric_service_with_endpoint() {
+        let builder = GCPMetricsExporter::builder().with_endpoint("https://monitoring.example.com");
+        assert_ignored_option(build_with_metric_service(builder).await, "endpoint");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_metric_service_with_credentials() {
+        let builder = GCPMetricsExporter::builder().with_credentials(CredentialsConfig::Anonymous);
+        assert_ignored_option(build_with_metric_service(builder).await, "credentials");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_metric_service_with_impersonated_service_account() {
+        let builder = GCPMetricsExporter::builder()
+            .with_impersonated_service_account("exporter@my-project.iam.gserviceaccount.com");
+        assert_ignored_option(build_with_metric_service(builder).await, "impersonate_service_account");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_metric_service_with_quota_project_id() {
+        let builder = GCPMetricsExporter::builder().with_quota_project_id("billing-project");
+        assert_ignored_option(build_with_metric_service(builder).await, "quota_project_id");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_metric_service_with_insecure() {
+        let builder = GCPMetricsExporter::builder().with_insecure(true);
+        assert_ignored_option(build_with_metric_service(builder).await, "insecure");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_metric_service_with_dry_run() {
+        let builder = GCPMetricsExporter::builder().with_dry_run(DryRunConfig::default());
+        assert_ignored_option(build_with_metric_service(builder).await, "dry_run");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_builder_missing_key_file() {
+        let res = GCPMetricsExporter::builder()
+            .with_credentials(CredentialsConfig::ServiceAccountKeyFile("/nonexistent/key.json".into()))
+            .build()
+            .await;
+        assert!(matches!(res, Err(GCPMetricsExporterInitError::LoadCredentials(_))));
+    }
+}
@@ -44,7 +44,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -76,7 +76,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         assert_eq!(
             create_time_series.time_series[0].points[0]
                 .interval
@@ -208,7 +208,7 @@ mod tests {
         }
         metrics_provider.force_flush().unwrap();
         let create_metric_descriptor = mock_service.expect_create_metric_descriptor().await;
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -236,7 +236,7 @@ mod tests {
         assert_eq_all_sorted!(create_metric_descriptor, expected_create_metric_descriptor);
 
         let create_time_series = mock_service.expect_create_time_series().await;
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         assert_eq!(
             create_time_series.time_series[0].points[0]
                 .interval
@@ -371,7 +371,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -403,7 +403,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         assert_eq!(
             create_time_series.time_series[0].points[0]
                 .interval
@@ -502,7 +502,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -534,7 +534,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -617,7 +617,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -649,7 +649,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -735,7 +735,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -767,7 +767,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -853,7 +853,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -885,7 +885,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -971,7 +971,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -1003,7 +1003,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -1089,7 +1089,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -1121,7 +1121,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -1208,7 +1208,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -1240,7 +1240,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -1326,7 +1326,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -1358,7 +1358,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -1443,7 +1443,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -1475,7 +1475,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -1560,7 +1560,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -1592,7 +1592,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -1670,7 +1670,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -1694,7 +1694,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -1786,7 +1786,7 @@ mod tests {
         //     println!("create_metric_descriptor -->");
         //     println!("{:#?}", v);
         // });
-        let create_metric_descriptor = create_metric_descriptor.get(0).unwrap().clone();
+        let create_metric_descriptor = create_metric_descriptor.first().unwrap().clone();
 
         let expected_create_metric_descriptor = google_cloud_monitoring_v3::model::CreateMetricDescriptorRequest::new()
             .set_name("projects/fake_project_id".to_string())
@@ -1818,7 +1818,7 @@ mod tests {
         //     println!("create_time_series -->");
         //     println!("{:#?}", v);
         // });
-        let mut create_time_series = create_time_series.get(0).unwrap().clone();
+        let mut create_time_series = create_time_series.first().unwrap().clone();
         //WARNING! need to ignore interval becouse its ignored in python tests
         assert_eq!(
             create_time_series.time_series[0].points[0]
@@ -1878,4 +1878,19 @@ mod tests {
             ]);
         assert_eq_sorted!(create_time_series, expected_create_time_series);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_time_series_permission_denied() {
+        let mock_service = MockMetricService::new();
+        let metrics_provider = init_metrics(mock_service.clone(), vec![KeyValue::new("service.name", "metric-demo")]);
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+        mock_service
+            .set_create_time_series_error(Some(google_cloud_gax::error::rpc::Code::PermissionDenied))
+            .await;
+
+        mycounter.add(1, &[]);
+
+        assert!(metrics_provider.force_flush().is_err());
+    }
 }
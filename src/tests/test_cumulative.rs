@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{ExportStatsHandle, GCPMetricsExporterConfig};
+
+    use google_cloud_wkt::Timestamp;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{SdkMeterProvider, Temporality};
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU64, Ordering},
+        },
+        time::Duration,
+    };
+
+    /// Exports the values of an observable counter, one export per value.
+    async fn export_observed(
+        config: GCPMetricsExporterConfig,
+        values: &[u64],
+    ) -> (Vec<(Timestamp, Timestamp)>, ExportStatsHandle) {
+        let mock_service = MockMetricService::new();
+        let (metrics_provider, stats) =
+            init_metrics_with_config(mock_service.clone(), config, SdkMeterProvider::builder());
+        let meter = metrics_provider.meter("test_cumulative");
+        let observed = Arc::new(AtomicU64::new(0));
+        let _counter = meter
+            .u64_observable_counter("connections")
+            .with_callback({
+                let observed = observed.clone();
+                move |observer| observer.observe(observed.load(Ordering::Relaxed), &[])
+            })
+            .build();
+        for value in values {
+            observed.store(*value, Ordering::Relaxed);
+            metrics_provider.force_flush().unwrap();
+            // a reset starts 1ms after the last point
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let intervals = mock_service
+            .expect_create_time_series()
+            .await
+            .into_iter()
+            .map(|req| {
+                let interval = req.time_series[0].points[0].interval.clone().unwrap();
+                (interval.start_time.unwrap(), interval.end_time.unwrap())
+            })
+            .collect();
+        (intervals, stats)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_cumulative_reset() {
+        let (intervals, stats) = export_observed(GCPMetricsExporterConfig::default(), &[10, 3, 5]).await;
+        assert_eq!(intervals.len(), 3);
+        // the counter went down, a new interval starts after the last point
+        assert!(intervals[1].0 > intervals[0].1);
+        assert!(intervals[1].0 < intervals[1].1);
+        assert_eq!(intervals[2].0, intervals[1].0);
+        assert_eq!(stats.stats().cumulative_resets, 1);
+        assert_eq!(stats.stats().time_series_dropped, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_cumulative_delta_temporality() {
+        // changes of 10, 3 and 2
+        let (intervals, stats) = export_observed(
+            GCPMetricsExporterConfig {
+                temporality: Temporality::Delta,
+                ..Default::default()
+            },
+            &[10, 13, 15],
+        )
+        .await;
+        assert_eq!(intervals.len(), 3);
+        // every point starts where the previous one ended
+        assert_eq!(intervals[1].0, intervals[0].1);
+        assert_eq!(intervals[2].0, intervals[1].1);
+        assert_eq!(stats.stats().cumulative_resets, 0);
+        assert_eq!(stats.stats().time_series_dropped, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_cumulative_reset_disabled() {
+        let (intervals, stats) = export_observed(
+            GCPMetricsExporterConfig {
+                handle_cumulative_resets: false,
+                ..Default::default()
+            },
+            &[10, 3],
+        )
+        .await;
+        assert_eq!(intervals[1].0, intervals[0].0);
+        assert_eq!(stats.stats().cumulative_resets, 0);
+    }
+}
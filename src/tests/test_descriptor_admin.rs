@@ -0,0 +1,200 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{
+        CredentialsConfig, DryRunConfig, DryRunFormat, DryRunOutput, GCPMetricsExporter, GCPMetricsExporterConfig,
+        MetricDescriptorAdmin, MetricDescriptorDiff, diff_metric_descriptors, read_metric_descriptors,
+    };
+
+    use google_cloud_api::model::{
+        LabelDescriptor, MetricDescriptor,
+        metric_descriptor::{MetricKind, ValueType},
+    };
+    use google_cloud_monitoring_v3::client::MetricService;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{SdkMeterProvider, periodic_reader_with_async_runtime::PeriodicReader};
+    use opentelemetry_sdk::runtime;
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::sync::{Arc, Mutex};
+
+    fn descriptor(metric_type: &str, value_type: ValueType) -> MetricDescriptor {
+        MetricDescriptor::new()
+            .set_type(metric_type)
+            .set_metric_kind(MetricKind::Cumulative)
+            .set_value_type(value_type)
+            .set_labels(vec![LabelDescriptor::new().set_key("key")])
+    }
+
+    /// Dry-run output of an app recording `counters`.
+    async fn dry_run_output(counters: &[&str]) -> Vec<u8> {
+        let buffer = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let exporter = GCPMetricsExporter::init(GCPMetricsExporterConfig {
+            dry_run: Some(DryRunConfig {
+                output: DryRunOutput::Writer(buffer.clone()),
+                format: DryRunFormat::Json,
+            }),
+            add_unique_identifier: false,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_descriptor_admin");
+        for name in counters {
+            meter
+                .u64_counter(name.to_string())
+                .build()
+                .add(1, &[opentelemetry::KeyValue::new("key", "value")]);
+        }
+        metrics_provider.force_flush().unwrap();
+        buffer.lock().unwrap().clone()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_read_metric_descriptors() {
+        let descriptors = read_metric_descriptors(&dry_run_output(&["requests", "errors"]).await).unwrap();
+        assert_eq!(
+            descriptors.iter().map(|d| d.r#type.as_str()).collect::<Vec<_>>(),
+            vec!["workload.googleapis.com/errors", "workload.googleapis.com/requests"]
+        );
+        assert_eq!(descriptors[0].value_type, ValueType::Int64);
+
+        let listed = serde_json::to_vec(&descriptors).unwrap();
+        assert_eq!(read_metric_descriptors(&listed).unwrap(), descriptors);
+        assert!(read_metric_descriptors(b"[{\"type\": 1}]").is_err());
+    }
+
+    #[test]
+    fn test_diff_metric_descriptors() {
+        let existing = vec![
+            descriptor("workload.googleapis.com/a", ValueType::Int64),
+            descriptor("workload.googleapis.com/b", ValueType::Int64),
+            descriptor("workload.googleapis.com/old", ValueType::Int64),
+        ];
+        let wanted = vec![
+            descriptor("workload.googleapis.com/a", ValueType::Int64),
+            descriptor("workload.googleapis.com/b", ValueType::Double),
+            descriptor("workload.googleapis.com/new", ValueType::Int64),
+        ];
+        assert_eq!(
+            diff_metric_descriptors(&existing, &wanted),
+            MetricDescriptorDiff {
+                missing: vec!["workload.googleapis.com/new".to_string()],
+                obsolete: vec!["workload.googleapis.com/old".to_string()],
+                changed: vec!["workload.googleapis.com/b".to_string()],
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_prune_obsolete_descriptors() {
+        let mock_service = MockMetricService::new();
+        for metric_type in ["requests", "old"] {
+            mock_service
+                .add_metric_descriptor(
+                    "fake_project_id",
+                    descriptor(&format!("workload.googleapis.com/{}", metric_type), ValueType::Int64),
+                )
+                .await;
+        }
+        mock_service
+            .add_metric_descriptor(
+                "fake_project_id",
+                descriptor("custom.googleapis.com/other", ValueType::Int64),
+            )
+            .await;
+        let admin = MetricDescriptorAdmin::new(MetricService::from_stub(mock_service.clone()), "fake_project_id");
+
+        let wanted = read_metric_descriptors(&dry_run_output(&["requests"]).await).unwrap();
+        let diff = diff_metric_descriptors(&admin.list("workload.googleapis.com/").await.unwrap(), &wanted);
+        assert_eq!(diff.obsolete, vec!["workload.googleapis.com/old".to_string()]);
+        admin.delete(&diff.obsolete).await.unwrap();
+
+        assert_eq!(
+            mock_service.delete_metric_descriptor_requests().await[0].name,
+            "projects/fake_project_id/metricDescriptors/workload.googleapis.com/old"
+        );
+        let listed = admin.list("workload.googleapis.com/").await.unwrap();
+        assert_eq!(
+            listed.iter().map(|d| d.r#type.as_str()).collect::<Vec<_>>(),
+            vec!["workload.googleapis.com/requests"]
+        );
+        assert!(
+            admin
+                .delete(&["workload.googleapis.com/old".to_string()])
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_descriptor_admin_with_fake_server() {
+        let server = FakeMonitoringServer::start(MockMetricService::new()).await.unwrap();
+        let admin = MetricDescriptorAdmin::from_config(&GCPMetricsExporterConfig {
+            project_id: Some("fake-project".to_string()),
+            endpoint: Some(server.endpoint().to_string()),
+            credentials: CredentialsConfig::Anonymous,
+            insecure: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let wanted = read_metric_descriptors(&dry_run_output(&["requests", "errors"]).await).unwrap();
+        admin.create(&wanted).await.unwrap();
+        let listed = admin.list("workload.googleapis.com/").await.unwrap();
+        assert_eq!(
+            diff_metric_descriptors(&listed, &wanted),
+            MetricDescriptorDiff::default()
+        );
+
+        admin
+            .delete(&["workload.googleapis.com/errors".to_string()])
+            .await
+            .unwrap();
+        let listed = admin.list("workload.googleapis.com/").await.unwrap();
+        assert_eq!(
+            listed.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(),
+            vec!["projects/fake-project/metricDescriptors/workload.googleapis.com/requests"]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_descriptor_admin_dry_run() {
+        let buffer = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let admin = MetricDescriptorAdmin::from_config(&GCPMetricsExporterConfig {
+            dry_run: Some(DryRunConfig {
+                output: DryRunOutput::Writer(buffer.clone()),
+                format: DryRunFormat::Ndjson,
+            }),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(admin.project_id(), "dry-run");
+        assert_eq!(admin.list("workload.googleapis.com/").await.unwrap(), vec![]);
+
+        admin
+            .create(&[descriptor("workload.googleapis.com/new", ValueType::Int64)])
+            .await
+            .unwrap();
+        admin
+            .delete(&["workload.googleapis.com/old".to_string()])
+            .await
+            .unwrap();
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let records = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["method"], "CreateMetricDescriptor");
+        assert_eq!(records[1]["method"], "DeleteMetricDescriptor");
+        assert_eq!(
+            records[1]["request"]["name"],
+            "projects/dry-run/metricDescriptors/workload.googleapis.com/old"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use crate::exporter::DryRunMetricService;
+    use crate::tests::test_utils::*;
+    use crate::{DryRunConfig, DryRunFormat, DryRunOutput, GCPMetricsExporterConfig};
+
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::sync::{Arc, Mutex};
+
+    fn init_dry_run_metrics(dry_run: DryRunConfig) -> SdkMeterProvider {
+        let dry_run_service = DryRunMetricService::new(dry_run).unwrap();
+        init_metrics_with_config(
+            dry_run_service,
+            GCPMetricsExporterConfig::default(),
+            SdkMeterProvider::builder(),
+        )
+        .0
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_dry_run_ndjson_writer() {
+        let buffer = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let metrics_provider = init_dry_run_metrics(DryRunConfig {
+            output: DryRunOutput::Writer(buffer.clone()),
+            format: DryRunFormat::Ndjson,
+        });
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+        mycounter.add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let records = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["method"], "CreateMetricDescriptor");
+        assert_eq!(records[0]["request"]["name"], "projects/fake_project_id");
+        assert_eq!(
+            records[0]["request"]["metricDescriptor"]["type"],
+            "workload.googleapis.com/mycounter"
+        );
+        assert_eq!(records[1]["method"], "CreateTimeSeries");
+        let req = serde_json::from_value::<google_cloud_monitoring_v3::model::CreateTimeSeriesRequest>(
+            records[1]["request"].clone(),
+        )
+        .unwrap();
+        assert_eq!(req.name, "projects/fake_project_id");
+        assert_eq!(req.time_series.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_dry_run_json_file() {
+        let path = std::env::temp_dir().join(format!("gcp_metrics_exporter_dry_run_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let metrics_provider = init_dry_run_metrics(DryRunConfig {
+            output: DryRunOutput::File(path.clone()),
+            format: DryRunFormat::Json,
+        });
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+        mycounter.add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let output = std::fs::read_to_string(&path).unwrap();
+        let records = serde_json::Deserializer::from_str(&output)
+            .into_iter::<serde_json::Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1]["method"], "CreateTimeSeries");
+        assert!(output.lines().count() > 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{ExportErrorHandler, ExportOperation, GCPMetricsExportError, GCPMetricsExporterConfig};
+
+    use google_cloud_gax::error::rpc::Code;
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::runtime;
+    use opentelemetry_sdk::{
+        Resource,
+        metrics::{SdkMeterProvider, periodic_reader_with_async_runtime::PeriodicReader},
+    };
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_on_error() {
+        let errors = Arc::new(Mutex::new(Vec::<GCPMetricsExportError>::new()));
+        let on_error = {
+            let errors = errors.clone();
+            ExportErrorHandler::new(move |err| errors.lock().unwrap().push(err.clone()))
+        };
+        let mock_service = MockMetricService::new();
+        let exporter = init_metrics_exporter_with_config(
+            mock_service.clone(),
+            GCPMetricsExporterConfig {
+                on_error: Some(on_error),
+                ..Default::default()
+            },
+        );
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_resource(
+                Resource::builder_empty()
+                    .with_attributes(vec![KeyValue::new("service.name", "metric-demo")])
+                    .build(),
+            )
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+
+        mycounter.add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+        assert!(errors.lock().unwrap().is_empty());
+
+        mock_service
+            .set_create_time_series_error(Some(Code::ResourceExhausted))
+            .await;
+        mycounter.add(1, &[]);
+        let flush_err = metrics_provider.force_flush().unwrap_err();
+        assert!(flush_err.to_string().contains("quota exceeded"));
+
+        let errors = errors.lock().unwrap().clone();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], GCPMetricsExportError::QuotaExceeded(_)));
+        let details = errors[0].details();
+        assert_eq!(details.operation, ExportOperation::CreateTimeSeries);
+        assert_eq!(errors[0].code(), Some(Code::ResourceExhausted));
+        assert_eq!(
+            details.metric_types,
+            vec!["workload.googleapis.com/mycounter".to_string()]
+        );
+        assert_eq!(details.time_series_count, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_permission_denied_error() {
+        let mock_service = MockMetricService::new();
+        let exporter = init_metrics_exporter_with_config(mock_service.clone(), GCPMetricsExporterConfig::default());
+        let stats = exporter.stats_handle();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+
+        mock_service
+            .set_create_time_series_error(Some(Code::PermissionDenied))
+            .await;
+        mycounter.add(1, &[]);
+        assert!(metrics_provider.force_flush().is_err());
+
+        let last_error = stats.stats().last_time_series_error.unwrap();
+        assert!(matches!(last_error, GCPMetricsExportError::PermissionDenied(_)));
+        assert!(last_error.to_string().contains("roles/monitoring.metricWriter"));
+    }
+}
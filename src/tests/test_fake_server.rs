@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{CredentialsConfig, GCPMetricsExportError, GCPMetricsExporter, GCPMetricsExporterConfig};
+
+    use google_cloud_gax::error::rpc::Code;
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{SdkMeterProvider, periodic_reader_with_async_runtime::PeriodicReader};
+    use opentelemetry_sdk::runtime;
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    fn fake_server_config(server: &FakeMonitoringServer) -> GCPMetricsExporterConfig {
+        GCPMetricsExporterConfig {
+            project_id: Some("fake-project".to_string()),
+            endpoint: Some(server.endpoint().to_string()),
+            credentials: CredentialsConfig::Anonymous,
+            insecure: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_init_with_fake_server() {
+        let server = FakeMonitoringServer::start(MockMetricService::new()).await.unwrap();
+        let exporter = GCPMetricsExporter::init(fake_server_config(&server)).await.unwrap();
+        let stats = exporter.stats_handle();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").with_unit("s").build();
+        mycounter.add(3, &[KeyValue::new("key", "value")]);
+        metrics_provider.force_flush().unwrap();
+
+        let descriptors = server.mock_service().expect_create_metric_descriptor().await;
+        assert_eq!(descriptors.len(), 1);
+        assert_eq!(descriptors[0].name, "projects/fake-project");
+        let descriptor = descriptors[0].metric_descriptor.as_ref().unwrap();
+        assert_eq!(descriptor.r#type, "workload.googleapis.com/mycounter");
+        assert_eq!(descriptor.unit, "s");
+
+        let time_series = server.mock_service().expect_create_time_series().await;
+        assert_eq!(time_series.len(), 1);
+        assert_eq!(time_series[0].name, "projects/fake-project");
+        let series = &time_series[0].time_series[0];
+        assert_eq!(series.metric.as_ref().unwrap().labels.get("key").unwrap(), "value");
+        assert_eq!(stats.stats().time_series_sent, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fake_server_errors() {
+        let server = FakeMonitoringServer::start(MockMetricService::new()).await.unwrap();
+        server
+            .mock_service()
+            .set_create_time_series_error(Some(Code::PermissionDenied))
+            .await;
+        let exporter = GCPMetricsExporter::init(fake_server_config(&server)).await.unwrap();
+        let stats = exporter.stats_handle();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+        mycounter.add(1, &[]);
+        assert!(metrics_provider.force_flush().is_err());
+
+        let last_error = stats.stats().last_time_series_error.unwrap();
+        assert!(matches!(last_error, GCPMetricsExportError::PermissionDenied(_)));
+        assert_eq!(last_error.code(), Some(Code::PermissionDenied));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_plain_http_endpoint_needs_insecure() {
+        let server = FakeMonitoringServer::start(MockMetricService::new()).await.unwrap();
+        let res = GCPMetricsExporter::init(GCPMetricsExporterConfig {
+            insecure: false,
+            ..fake_server_config(&server)
+        })
+        .await;
+        assert!(matches!(res, Err(crate::GCPMetricsExporterInitError::InvalidConfig(_))));
+    }
+}
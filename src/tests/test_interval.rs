@@ -0,0 +1,194 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{ExportStatsHandle, GCPMetricsExporterConfig};
+
+    use google_cloud_api::model::metric_descriptor::MetricKind;
+    use google_cloud_monitoring_v3::model::{TimeInterval, TimeSeries};
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{Aggregation, Instrument, SdkMeterProvider, Stream, Temporality};
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::collections::HashMap;
+
+    /// Records one value of every instrument, `exports` times, and returns
+    /// the time series of every export by instrument name. Observable
+    /// instruments observe the same value on every export.
+    /// `exp_histogram` is a base 2 exponential histogram.
+    async fn export_instruments(
+        config: GCPMetricsExporterConfig,
+        exports: usize,
+    ) -> (Vec<HashMap<String, TimeSeries>>, ExportStatsHandle) {
+        let mock_service = MockMetricService::new();
+        let builder = SdkMeterProvider::builder().with_view(|instrument: &Instrument| {
+            (instrument.name() == "exp_histogram")
+                .then(|| {
+                    Stream::builder()
+                        .with_aggregation(Aggregation::Base2ExponentialHistogram {
+                            max_size: 160,
+                            max_scale: 20,
+                            record_min_max: true,
+                        })
+                        .build()
+                        .ok()
+                })
+                .flatten()
+        });
+        let (metrics_provider, stats) = init_metrics_with_config(mock_service.clone(), config, builder);
+        let meter = metrics_provider.meter("test_interval");
+        let counter = meter.u64_counter("counter").build();
+        let up_down_counter = meter.i64_up_down_counter("up_down_counter").build();
+        let gauge = meter.f64_gauge("gauge").build();
+        let histogram = meter.f64_histogram("histogram").build();
+        let exp_histogram = meter.f64_histogram("exp_histogram").build();
+        let _observable_counter = meter
+            .u64_observable_counter("observable_counter")
+            .with_callback(|observer| observer.observe(3, &[]))
+            .build();
+        let _observable_up_down_counter = meter
+            .i64_observable_up_down_counter("observable_up_down_counter")
+            .with_callback(|observer| observer.observe(-3, &[]))
+            .build();
+        let _observable_gauge = meter
+            .f64_observable_gauge("observable_gauge")
+            .with_callback(|observer| observer.observe(0.5, &[]))
+            .build();
+        for _ in 0..exports {
+            counter.add(2, &[]);
+            // goes down on every export
+            up_down_counter.add(-1, &[]);
+            gauge.record(0.5, &[]);
+            histogram.record(1.0, &[]);
+            exp_histogram.record(1.0, &[]);
+            metrics_provider.force_flush().unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+
+        let exported = mock_service
+            .expect_create_time_series()
+            .await
+            .into_iter()
+            .map(|req| {
+                req.time_series
+                    .into_iter()
+                    .map(|series| {
+                        let name = series.metric.as_ref().unwrap().r#type.clone();
+                        (name.trim_start_matches("workload.googleapis.com/").to_string(), series)
+                    })
+                    .collect()
+            })
+            .collect();
+        (exported, stats)
+    }
+
+    fn interval(series: &TimeSeries) -> TimeInterval {
+        series.points[0].interval.clone().unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_interval_default() {
+        let (exported, _) = export_instruments(GCPMetricsExporterConfig::default(), 1).await;
+        let series = &exported[0];
+        for (name, kind, has_start_time) in [
+            ("counter", MetricKind::Cumulative, true),
+            ("up_down_counter", MetricKind::Gauge, false),
+            ("gauge", MetricKind::Gauge, false),
+            ("histogram", MetricKind::Cumulative, true),
+            ("exp_histogram", MetricKind::Cumulative, true),
+            ("observable_counter", MetricKind::Cumulative, true),
+            ("observable_up_down_counter", MetricKind::Gauge, false),
+            ("observable_gauge", MetricKind::Gauge, false),
+        ] {
+            assert_eq!(series[name].metric_kind, kind, "{}", name);
+            let interval = interval(&series[name]);
+            assert_eq!(interval.start_time.is_some(), has_start_time, "{}", name);
+            if let Some(start_time) = interval.start_time {
+                assert!(start_time < interval.end_time.unwrap(), "{}", name);
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_interval_delta() {
+        let (exported, stats) = export_instruments(
+            GCPMetricsExporterConfig {
+                temporality: Temporality::Delta,
+                ..Default::default()
+            },
+            2,
+        )
+        .await;
+        let (first, second) = (&exported[0], &exported[1]);
+        for (name, kind, has_start_time) in [
+            ("counter", MetricKind::Cumulative, true),
+            ("up_down_counter", MetricKind::Gauge, false),
+            ("gauge", MetricKind::Gauge, false),
+            ("histogram", MetricKind::Cumulative, true),
+            ("exp_histogram", MetricKind::Cumulative, true),
+            ("observable_counter", MetricKind::Cumulative, true),
+            ("observable_up_down_counter", MetricKind::Gauge, false),
+            ("observable_gauge", MetricKind::Gauge, false),
+        ] {
+            assert_eq!(second[name].metric_kind, kind, "{}", name);
+            let interval = interval(&second[name]);
+            assert_eq!(interval.start_time.is_some(), has_start_time, "{}", name);
+            if has_start_time {
+                // the next window, not a reset moved after the last point
+                assert_eq!(interval.start_time, self::interval(&first[name]).end_time, "{}", name);
+                assert!(interval.start_time.unwrap() < interval.end_time.unwrap(), "{}", name);
+            }
+        }
+        // the change since the previous export
+        assert_eq!(
+            second["counter"].points[0].value.as_ref().unwrap().int64_value(),
+            Some(&2)
+        );
+        assert_eq!(
+            second["observable_counter"].points[0]
+                .value
+                .as_ref()
+                .unwrap()
+                .int64_value(),
+            Some(&0)
+        );
+        assert_eq!(stats.stats().cumulative_resets, 0);
+        assert_eq!(stats.stats().time_series_dropped, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_interval_counters_as_gauge() {
+        let (exported, _) = export_instruments(
+            GCPMetricsExporterConfig {
+                counters_as_gauge: true,
+                ..Default::default()
+            },
+            1,
+        )
+        .await;
+        let counter = &exported[0]["counter"];
+        assert_eq!(counter.metric_kind, MetricKind::Gauge);
+        assert_eq!(interval(counter).start_time, None);
+        assert_eq!(counter.points[0].value.as_ref().unwrap().int64_value(), Some(&2));
+        // histograms stay cumulative
+        assert_eq!(exported[0]["histogram"].metric_kind, MetricKind::Cumulative);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_interval_up_down_counters_as_cumulative() {
+        let (exported, stats) = export_instruments(
+            GCPMetricsExporterConfig {
+                up_down_counters_as_cumulative: true,
+                ..Default::default()
+            },
+            2,
+        )
+        .await;
+        let first = &exported[0]["up_down_counter"];
+        let second = &exported[1]["up_down_counter"];
+        assert_eq!(first.metric_kind, MetricKind::Cumulative);
+        // fixed start time for the rate aligners, going down is not a reset
+        assert!(interval(first).start_time.is_some());
+        assert_eq!(interval(second).start_time, interval(first).start_time);
+        assert_eq!(second.points[0].value.as_ref().unwrap().int64_value(), Some(&-2));
+        assert_eq!(stats.stats().cumulative_resets, 0);
+    }
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+
+    use google_cloud_gax::error::rpc::Code;
+    use opentelemetry::metrics::MeterProvider;
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_mock_metric_service() {
+        let mock_service = MockMetricService::new();
+        let metrics_provider = init_metrics(mock_service.clone(), vec![]);
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+
+        mock_service
+            .push_errors(MockMethod::CreateTimeSeries, [Code::Unavailable, Code::Internal])
+            .await;
+        mycounter.add(1, &[]);
+        assert!(metrics_provider.force_flush().is_err());
+        assert!(metrics_provider.force_flush().is_err());
+        assert_eq!(mock_service.create_time_series_requests().await.len(), 0);
+        assert_eq!(mock_service.create_metric_descriptor_requests().await.len(), 1);
+
+        mock_service.set_latency(Some(Duration::from_millis(50))).await;
+        let started = Instant::now();
+        metrics_provider.force_flush().unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(mock_service.create_time_series_requests().await.len(), 1);
+
+        mock_service
+            .set_error(MockMethod::CreateTimeSeries, Some(Code::InvalidArgument))
+            .await;
+        assert!(metrics_provider.force_flush().is_err());
+
+        mock_service.reset().await;
+        assert_eq!(mock_service.create_time_series_requests().await.len(), 0);
+        metrics_provider.force_flush().unwrap();
+        assert_eq!(mock_service.create_time_series_requests().await.len(), 1);
+        // the descriptor is cached by the exporter, reset does not make it create it again
+        assert_eq!(mock_service.create_metric_descriptor_requests().await.len(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_mock_metric_service_unimplemented() {
+        let client = google_cloud_monitoring_v3::client::MetricService::from_stub(MockMetricService::new());
+        let err = client
+            .list_time_series()
+            .set_name("projects/fake_project_id")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status().map(|status| status.code), Some(Code::Unimplemented));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid exporter config")]
+    fn test_init_metrics_exporter_with_invalid_config() {
+        init_metrics_exporter_with_config(
+            MockMetricService::new(),
+            crate::GCPMetricsExporterConfig {
+                prefix: String::new(),
+                ..Default::default()
+            },
+        );
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{
+        GCPMetricsExporter, GCPMetricsExporterConfig, GCPMetricsExporterInitError, GenericNodeMapper,
+        MonitoredResourceDataConfig, MonitoredResourceMapper, MonitoredResourceResolver,
+    };
+
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{SdkMeterProvider, periodic_reader_with_async_runtime::PeriodicReader};
+    use opentelemetry_sdk::runtime;
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::collections::HashMap;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    #[derive(Debug, Default)]
+    struct CountingMapper {
+        calls: AtomicUsize,
+    }
+
+    impl MonitoredResourceMapper for CountingMapper {
+        fn map(&self, resource: &opentelemetry_sdk::Resource) -> Option<MonitoredResourceDataConfig> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            GenericNodeMapper.map(resource)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_monitored_resource_is_cached() {
+        let mock_service = MockMetricService::new();
+        let mapper = Arc::new(CountingMapper::default());
+        let exporter = init_metrics_exporter_with_config(
+            mock_service.clone(),
+            GCPMetricsExporterConfig {
+                monitored_resource_mapper: mapper.clone(),
+                ..Default::default()
+            },
+        );
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter.u64_counter("mycounter").build().add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+        metrics_provider.force_flush().unwrap();
+
+        assert_eq!(mapper.calls.load(Ordering::SeqCst), 1);
+        let requests = mock_service.create_time_series_requests().await;
+        assert_eq!(requests.len(), 2);
+        assert!(
+            requests
+                .iter()
+                .all(|req| req.time_series[0].resource.as_ref().unwrap().r#type == "generic_node")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_monitored_resource_from_attributes() {
+        let mock_service = MockMetricService::new();
+        let exporter = init_metrics_exporter_with_config(
+            mock_service.clone(),
+            GCPMetricsExporterConfig {
+                monitored_resource_resolvers: vec![MonitoredResourceResolver::Attributes {
+                    metric_names: vec![],
+                    resource: MonitoredResourceDataConfig {
+                        r#type: "gcs_bucket".to_string(),
+                        labels: HashMap::from([
+                            ("project_id".to_string(), "fake_project_id".to_string()),
+                            ("location".to_string(), "europe-west1".to_string()),
+                        ]),
+                    },
+                    attribute_labels: HashMap::from([("bucket_name".to_string(), "bucket".to_string())]),
+                }],
+                ..Default::default()
+            },
+        );
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let objects = meter.u64_counter("objects").build();
+        objects.add(
+            1,
+            &[KeyValue::new("bucket", "my-bucket"), KeyValue::new("kind", "image")],
+        );
+        metrics_provider.force_flush().unwrap();
+
+        let descriptor = mock_service.expect_create_metric_descriptor().await[0]
+            .metric_descriptor
+            .clone()
+            .unwrap();
+        assert_eq!(
+            descriptor
+                .labels
+                .iter()
+                .map(|label| label.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["kind"]
+        );
+        let time_series = mock_service.expect_create_time_series().await[0].time_series[0].clone();
+        let resource = time_series.resource.unwrap();
+        assert_eq!(resource.r#type, "gcs_bucket");
+        assert_eq!(resource.labels.get("bucket_name"), Some(&"my-bucket".to_string()));
+        assert_eq!(
+            time_series.metric.unwrap().labels,
+            HashMap::from([("kind".to_string(), "image".to_string())])
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_check_monitored_resource_descriptors() {
+        let mock_service = MockMetricService::new();
+        mock_service
+            .add_monitored_resource_descriptor(
+                google_cloud_api::model::MonitoredResourceDescriptor::new()
+                    .set_type("my_device")
+                    .set_labels(
+                        ["project_id", "device_id"]
+                            .map(|key| google_cloud_api::model::LabelDescriptor::new().set_key(key)),
+                    ),
+            )
+            .await;
+        let build = |r#type: &str, label: &str| {
+            GCPMetricsExporter::builder()
+                .with_metric_service(google_cloud_monitoring_v3::client::MetricService::from_stub(
+                    mock_service.clone(),
+                ))
+                .with_project_id("my-project")
+                .with_custom_monitored_resource_data(MonitoredResourceDataConfig {
+                    r#type: r#type.to_string(),
+                    labels: HashMap::from([(label.to_string(), "value".to_string())]),
+                })
+                .with_monitored_resource_descriptor_check(true)
+                .build()
+        };
+
+        assert!(build("my_device", "device_id").await.is_ok());
+        let err = build("my_device", "serial").await.unwrap_err();
+        assert!(matches!(err, GCPMetricsExporterInitError::InvalidConfig(_)));
+        assert!(err.to_string().contains("device_id"), "{}", err);
+        let err = build("my_other_device", "device_id").await.unwrap_err();
+        assert!(err.to_string().contains("unknown monitored resource type"), "{}", err);
+    }
+}
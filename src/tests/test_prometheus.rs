@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{GCPMetricsExporterConfig, PrometheusConfig};
+
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::runtime;
+    use opentelemetry_sdk::{
+        Resource,
+        metrics::{SdkMeterProvider, periodic_reader_with_async_runtime::PeriodicReader},
+    };
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_prometheus_mode() {
+        let mock_service = MockMetricService::new();
+        let exporter = init_metrics_exporter_with_config(
+            mock_service.clone(),
+            GCPMetricsExporterConfig {
+                prometheus: Some(PrometheusConfig::default()),
+                ..Default::default()
+            },
+        );
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_resource(
+                Resource::builder_empty()
+                    .with_attributes(vec![
+                        KeyValue::new("service.name", "checkout"),
+                        KeyValue::new("service.instance.id", "checkout-1"),
+                        KeyValue::new("cloud.region", "europe-west1"),
+                    ])
+                    .build(),
+            )
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter
+            .u64_counter("http.requests")
+            .with_unit("{request}")
+            .build()
+            .add(3, &[KeyValue::new("http.method", "GET")]);
+        meter.i64_gauge("queue.size").build().record(7, &[]);
+        meter
+            .f64_histogram("http.server.duration")
+            .with_unit("s")
+            .build()
+            .record(0.2, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        assert!(mock_service.create_metric_descriptor_requests().await.is_empty());
+        let mut time_series = mock_service.expect_create_time_series().await[0].time_series.clone();
+        time_series.sort_by(|a, b| {
+            a.metric
+                .as_ref()
+                .unwrap()
+                .r#type
+                .cmp(&b.metric.as_ref().unwrap().r#type)
+        });
+        assert_eq!(
+            time_series
+                .iter()
+                .map(|ts| ts.metric.as_ref().unwrap().r#type.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "prometheus.googleapis.com/http_requests_total/counter",
+                "prometheus.googleapis.com/http_server_duration_seconds/histogram",
+                "prometheus.googleapis.com/queue_size/gauge",
+            ]
+        );
+        let resource = time_series[0].resource.clone().unwrap();
+        assert_eq!(resource.r#type, "prometheus_target");
+        assert_eq!(resource.labels.get("job"), Some(&"checkout".to_string()));
+        assert_eq!(resource.labels.get("instance"), Some(&"checkout-1".to_string()));
+        assert_eq!(
+            time_series[0].value_type,
+            google_cloud_api::model::metric_descriptor::ValueType::Double
+        );
+        assert_eq!(
+            time_series[0].points[0].value.as_ref().unwrap().double_value(),
+            Some(&3.0)
+        );
+        assert_eq!(
+            time_series[2].points[0].value.as_ref().unwrap().double_value(),
+            Some(&7.0)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_prometheus_mode_up_down_counters_as_cumulative() {
+        let mock_service = MockMetricService::new();
+        let exporter = init_metrics_exporter_with_config(
+            mock_service.clone(),
+            GCPMetricsExporterConfig {
+                prometheus: Some(PrometheusConfig::default()),
+                up_down_counters_as_cumulative: true,
+                ..Default::default()
+            },
+        );
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter.i64_up_down_counter("queue.size").build().add(-2, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let time_series = mock_service.expect_create_time_series().await[0].time_series[0].clone();
+        // goes down, not a counter
+        assert_eq!(
+            time_series.metric.unwrap().r#type,
+            "prometheus.googleapis.com/queue_size/gauge"
+        );
+        assert_eq!(
+            time_series.metric_kind,
+            google_cloud_api::model::metric_descriptor::MetricKind::Cumulative
+        );
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{GCPMetricsExporterConfig, ProjectRouting};
+
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::{Resource, metrics::SdkMeterProvider};
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    fn init_routed_metrics(
+        mock_service: MockMetricService,
+        project_routing: ProjectRouting,
+        res_attributes: Vec<KeyValue>,
+    ) -> SdkMeterProvider {
+        init_metrics_with_config(
+            mock_service,
+            GCPMetricsExporterConfig {
+                project_routing,
+                ..Default::default()
+            },
+            SdkMeterProvider::builder()
+                .with_resource(Resource::builder_empty().with_attributes(res_attributes).build()),
+        )
+        .0
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_route_by_metric_attribute() {
+        let mock_service = MockMetricService::new();
+        let metrics_provider = init_routed_metrics(
+            mock_service.clone(),
+            ProjectRouting::MetricAttribute("tenant.project".to_string()),
+            vec![KeyValue::new("service.name", "metric-demo")],
+        );
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+        mycounter.add(1, &[KeyValue::new("tenant.project", "project-a")]);
+        mycounter.add(2, &[KeyValue::new("tenant.project", "project-b")]);
+        mycounter.add(3, &[KeyValue::new("other", "value")]);
+        metrics_provider.force_flush().unwrap();
+
+        let mut descriptor_projects = mock_service
+            .create_metric_descriptor_requests()
+            .await
+            .into_iter()
+            .map(|req| req.name)
+            .collect::<Vec<_>>();
+        descriptor_projects.sort();
+        assert_eq!(
+            descriptor_projects,
+            vec![
+                "projects/fake_project_id".to_string(),
+                "projects/project-a".to_string(),
+                "projects/project-b".to_string(),
+            ]
+        );
+
+        let mut series_projects = mock_service
+            .create_time_series_requests()
+            .await
+            .into_iter()
+            .map(|req| (req.name, req.time_series.len()))
+            .collect::<Vec<_>>();
+        series_projects.sort();
+        assert_eq!(
+            series_projects,
+            vec![
+                ("projects/fake_project_id".to_string(), 1),
+                ("projects/project-a".to_string(), 1),
+                ("projects/project-b".to_string(), 1),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_route_by_resource_attribute() {
+        let mock_service = MockMetricService::new();
+        let metrics_provider = init_routed_metrics(
+            mock_service.clone(),
+            ProjectRouting::ResourceAttribute("gcp.project_id".to_string()),
+            vec![KeyValue::new("gcp.project_id", "project-a")],
+        );
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter.u64_counter("mycounter").build().add(1, &[]);
+        meter.f64_gauge("mygauge").build().record(1.0, &[]);
+        metrics_provider.force_flush().unwrap();
+        metrics_provider.force_flush().unwrap();
+
+        // descriptors are cached per project
+        let descriptor_requests = mock_service.create_metric_descriptor_requests().await;
+        assert_eq!(descriptor_requests.len(), 2);
+        assert!(descriptor_requests.iter().all(|req| req.name == "projects/project-a"));
+        let series_requests = mock_service.create_time_series_requests().await;
+        assert_eq!(series_requests.len(), 2);
+        assert!(series_requests.iter().all(|req| req.name == "projects/project-a"));
+        assert_eq!(series_requests[0].time_series.len(), 2);
+    }
+}
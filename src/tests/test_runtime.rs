@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{ExportOutcome, GCPMetricsExporterConfig};
+
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
+    use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    /// The SDK's thread based reader, no async runtime involved.
+    #[test]
+    fn test_thread_periodic_reader_without_runtime() {
+        let exporter = init_metrics_exporter_with_config(MockMetricService::new(), GCPMetricsExporterConfig::default());
+        let stats = exporter.stats_handle();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter.u64_counter("mycounter").build().add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let stats = stats.stats();
+        assert_eq!(stats.last_export_outcome, Some(ExportOutcome::Success));
+        assert_eq!(stats.time_series_sent, 1);
+    }
+
+    /// Exports from the reader thread run on the runtime the exporter was
+    /// built on, the HTTP client needs its reactor.
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_thread_periodic_reader_with_fake_server() {
+        let server = FakeMonitoringServer::start(MockMetricService::new()).await.unwrap();
+        let exporter = crate::GCPMetricsExporter::init(GCPMetricsExporterConfig {
+            project_id: Some("fake-project".to_string()),
+            endpoint: Some(server.endpoint().to_string()),
+            credentials: crate::CredentialsConfig::Anonymous,
+            insecure: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter.u64_counter("mycounter").build().add(1, &[]);
+        let flush_provider = metrics_provider.clone();
+        tokio::task::spawn_blocking(move || flush_provider.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let time_series = server.mock_service().expect_create_time_series().await;
+        assert_eq!(time_series[0].name, "projects/fake-project");
+    }
+
+    /// Collects one counter point with the SDK's in memory exporter, to hand
+    /// it to `GCPMetricsExporter::export` from any executor.
+    fn collect_counter() -> opentelemetry_sdk::metrics::data::ResourceMetrics {
+        let in_memory = InMemoryMetricExporter::default();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(in_memory.clone()).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter.u64_counter("mycounter").build().add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+        in_memory.get_finished_metrics().unwrap().remove(0)
+    }
+
+    /// Plain futures executor, no tokio runtime around at all.
+    #[test]
+    fn test_export_with_futures_executor() {
+        let exporter = init_metrics_exporter_with_config(MockMetricService::new(), GCPMetricsExporterConfig::default());
+        let metrics = collect_counter();
+
+        futures_executor::block_on(exporter.export(&metrics)).unwrap();
+
+        let stats = exporter.stats_handle().stats();
+        assert_eq!(stats.last_export_outcome, Some(ExportOutcome::Success));
+        assert_eq!(stats.time_series_sent, 1);
+    }
+
+    /// Exports from a non tokio thread do not need the current_thread runtime
+    /// the exporter was built on to be running when the stub does not use it.
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_thread_periodic_reader_with_current_thread_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let exporter = {
+            let _guard = runtime.enter();
+            init_metrics_exporter_with_config(MockMetricService::new(), GCPMetricsExporterConfig::default())
+        };
+        let stats = exporter.stats_handle();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        meter.u64_counter("mycounter").build().add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let stats = stats.stats();
+        assert_eq!(stats.last_export_outcome, Some(ExportOutcome::Success));
+        assert_eq!(stats.time_series_sent, 1);
+    }
+
+    /// The HTTP client reaches the fake server from a futures executor on a
+    /// plain thread while the current_thread runtime it was built on keeps
+    /// running on the test thread.
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_export_with_futures_executor_on_current_thread_runtime() {
+        let server = FakeMonitoringServer::start(MockMetricService::new()).await.unwrap();
+        let exporter = crate::GCPMetricsExporter::init(GCPMetricsExporterConfig {
+            project_id: Some("fake-project".to_string()),
+            endpoint: Some(server.endpoint().to_string()),
+            credentials: crate::CredentialsConfig::Anonymous,
+            insecure: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let metrics = collect_counter();
+        let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+        std::thread::spawn(move || {
+            let _ = result_tx.send(futures_executor::block_on(exporter.export(&metrics)));
+        });
+        result_rx.await.unwrap().unwrap();
+
+        let time_series = server.mock_service().expect_create_time_series().await;
+        assert_eq!(time_series[0].name, "projects/fake-project");
+    }
+
+    /// The Cloud Monitoring client only runs on tokio.
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_init_outside_of_tokio_runtime() {
+        use std::future::Future;
+        use std::task::{Context, Poll, Waker};
+
+        let mut init = std::pin::pin!(crate::GCPMetricsExporter::init(GCPMetricsExporterConfig {
+            project_id: Some("fake-project".to_string()),
+            credentials: crate::CredentialsConfig::Anonymous,
+            ..Default::default()
+        }));
+        let poll = init.as_mut().poll(&mut Context::from_waker(Waker::noop()));
+        assert!(matches!(
+            poll,
+            Poll::Ready(Err(crate::GCPMetricsExporterInitError::InvalidConfig(_)))
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{
+        ExportStatsHandle, GCPMetricsExporterConfig, MetricSchema, MetricSchemaEntry, MonitoredResourceDataConfig,
+        UndeclaredPolicy,
+    };
+
+    use google_cloud_api::model::{LabelDescriptor, label_descriptor};
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use pretty_assertions_sorted_fork::assert_eq;
+    use std::collections::HashMap;
+
+    fn schema(undeclared: UndeclaredPolicy) -> MetricSchema {
+        let mut schema = MetricSchema::from_json(
+            r#"{
+                "metrics": [{
+                    "name": "requests",
+                    "metric_kind": "CUMULATIVE",
+                    "value_type": "DOUBLE",
+                    "unit": "{request}",
+                    "description": "Handled requests.",
+                    "labels": [
+                        {"key": "route", "description": "Route template."},
+                        {"key": "status", "description": "Status code.", "value_type": "INT64"}
+                    ],
+                    "monitored_resource_types": ["generic_task"]
+                }, {
+                    "name": "latency",
+                    "metric_kind": "GAUGE",
+                    "value_type": "INT64"
+                }]
+            }"#,
+        )
+        .unwrap();
+        schema.undeclared = undeclared;
+        schema
+    }
+
+    fn init_schema_metrics(
+        mock_service: MockMetricService,
+        schema: MetricSchema,
+    ) -> (SdkMeterProvider, ExportStatsHandle) {
+        init_metrics_with_config(
+            mock_service,
+            GCPMetricsExporterConfig {
+                schema: Some(schema),
+                custom_monitored_resource_data: Some(MonitoredResourceDataConfig {
+                    r#type: "generic_task".to_string(),
+                    labels: HashMap::from([
+                        ("location".to_string(), "europe-west1".to_string()),
+                        ("namespace".to_string(), "shop".to_string()),
+                        ("job".to_string(), "checkout".to_string()),
+                        ("task_id".to_string(), "1".to_string()),
+                    ]),
+                }),
+                ..Default::default()
+            },
+            SdkMeterProvider::builder(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_schema_warn() {
+        let mock_service = MockMetricService::new();
+        let (metrics_provider, _) = init_schema_metrics(mock_service.clone(), schema(UndeclaredPolicy::Warn));
+        let meter = metrics_provider.meter("test_schema");
+        meter.u64_counter("requests").build().add(
+            1,
+            &[
+                KeyValue::new("route", "/cart"),
+                KeyValue::new("status", 200),
+                KeyValue::new("user", "alice"),
+            ],
+        );
+        meter.u64_counter("undeclared").build().add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let descriptors = mock_service
+            .expect_create_metric_descriptor()
+            .await
+            .into_iter()
+            .map(|req| req.metric_descriptor.unwrap())
+            .map(|descriptor| (descriptor.r#type.clone(), descriptor))
+            .collect::<HashMap<_, _>>();
+        let requests = &descriptors["workload.googleapis.com/requests"];
+        assert_eq!(requests.description, "Handled requests.");
+        assert_eq!(requests.unit, "{request}");
+        assert_eq!(
+            requests.labels,
+            vec![
+                LabelDescriptor::new()
+                    .set_key("route")
+                    .set_description("Route template."),
+                LabelDescriptor::new()
+                    .set_key("status")
+                    .set_description("Status code.")
+                    .set_value_type(label_descriptor::ValueType::Int64),
+                // undeclared, kept so the time series can be written
+                LabelDescriptor::new().set_key("user"),
+            ]
+        );
+        assert!(descriptors.contains_key("workload.googleapis.com/undeclared"));
+
+        let time_series = mock_service.expect_create_time_series().await[0].time_series.clone();
+        assert_eq!(time_series.len(), 2);
+        let requests = time_series
+            .iter()
+            .find(|series| series.metric.as_ref().unwrap().r#type == "workload.googleapis.com/requests")
+            .unwrap();
+        assert_eq!(
+            requests.metric.as_ref().unwrap().labels,
+            HashMap::from([
+                ("route".to_string(), "/cart".to_string()),
+                ("status".to_string(), "200".to_string()),
+                ("user".to_string(), "alice".to_string()),
+            ])
+        );
+        assert_eq!(
+            requests.value_type,
+            google_cloud_api::model::metric_descriptor::ValueType::Double
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_schema_reject() {
+        let mock_service = MockMetricService::new();
+        let mut schema = schema(UndeclaredPolicy::Reject);
+        schema.metrics[1].monitored_resource_types = vec!["k8s_container".to_string()];
+        schema.metrics.push(MetricSchemaEntry {
+            name: "ratio".to_string(),
+            ..schema.metrics[1].clone()
+        });
+        let (metrics_provider, stats) = init_schema_metrics(mock_service.clone(), schema);
+        let meter = metrics_provider.meter("test_schema");
+        let requests = meter.u64_counter("requests").build();
+        requests.add(1, &[KeyValue::new("route", "/cart")]);
+        requests.add(1, &[KeyValue::new("route", "/cart"), KeyValue::new("user", "alice")]);
+        meter.u64_counter("undeclared").build().add(1, &[]);
+        // only declared for k8s_container
+        meter.i64_gauge("latency").build().record(5, &[]);
+        // declared as an INT64 gauge
+        meter.f64_gauge("ratio").build().record(0.5, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        // metrics without any time series left have no descriptor created
+        let descriptors = mock_service.expect_create_metric_descriptor().await;
+        assert_eq!(descriptors.len(), 1);
+        let time_series = mock_service.expect_create_time_series().await[0].time_series.clone();
+        assert_eq!(time_series.len(), 1);
+        assert_eq!(stats.stats().time_series_dropped, 4);
+        assert_eq!(
+            time_series[0].metric.as_ref().unwrap().labels,
+            HashMap::from([("route".to_string(), "/cart".to_string())])
+        );
+    }
+}
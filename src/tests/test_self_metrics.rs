@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use crate::GCPMetricsExporterConfig;
+    use crate::tests::test_utils::*;
+
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData, ResourceMetrics};
+    use opentelemetry_sdk::runtime;
+    use opentelemetry_sdk::{
+        Resource,
+        metrics::periodic_reader_with_async_runtime::PeriodicReader,
+        metrics::{InMemoryMetricExporter, PeriodicReader as ThreadPeriodicReader, SdkMeterProvider},
+    };
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    fn u64_sum(metrics: &[ResourceMetrics], name: &str, attribute: Option<(&str, &str)>) -> u64 {
+        let mut total = 0;
+        for resource_metrics in metrics {
+            for scope_metrics in resource_metrics.scope_metrics() {
+                for metric in scope_metrics.metrics().filter(|metric| metric.name() == name) {
+                    if let AggregatedMetrics::U64(MetricData::Sum(sum)) = metric.data() {
+                        for data_point in sum.data_points() {
+                            let matches = attribute.is_none_or(|(key, value)| {
+                                data_point
+                                    .attributes()
+                                    .any(|kv| kv.key.as_str() == key && kv.value.as_str() == value)
+                            });
+                            if matches {
+                                total = data_point.value();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        total
+    }
+
+    fn histogram_count(metrics: &[ResourceMetrics], name: &str) -> u64 {
+        let mut count = 0;
+        for resource_metrics in metrics {
+            for scope_metrics in resource_metrics.scope_metrics() {
+                for metric in scope_metrics.metrics().filter(|metric| metric.name() == name) {
+                    if let AggregatedMetrics::F64(MetricData::Histogram(histogram)) = metric.data() {
+                        count = histogram.data_points().map(|data_point| data_point.count()).sum();
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_self_metrics() {
+        let self_metrics_exporter = InMemoryMetricExporter::default();
+        let self_metrics_provider = SdkMeterProvider::builder()
+            .with_reader(ThreadPeriodicReader::builder(self_metrics_exporter.clone()).build())
+            .build();
+
+        let mock_service = MockMetricService::new();
+        let exporter = init_metrics_exporter_with_config(
+            mock_service.clone(),
+            GCPMetricsExporterConfig {
+                self_metrics_meter: Some(self_metrics_provider.meter("gcp_metrics_exporter")),
+                ..Default::default()
+            },
+        );
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_resource(
+                Resource::builder_empty()
+                    .with_attributes(vec![KeyValue::new("service.name", "metric-demo")])
+                    .build(),
+            )
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+
+        mycounter.add(1, &[KeyValue::new("key", "a")]);
+        mycounter.add(1, &[KeyValue::new("key", "b")]);
+        metrics_provider.force_flush().unwrap();
+
+        mock_service
+            .set_create_time_series_error(Some(google_cloud_gax::error::rpc::Code::InvalidArgument))
+            .await;
+        mycounter.add(1, &[KeyValue::new("key", "a")]);
+        assert!(metrics_provider.force_flush().is_err());
+
+        self_metrics_provider.force_flush().unwrap();
+        let self_metrics = self_metrics_exporter.get_finished_metrics().unwrap();
+        assert_eq!(u64_sum(&self_metrics, "gcp_metrics_exporter.time_series.sent", None), 2);
+        assert_eq!(
+            u64_sum(&self_metrics, "gcp_metrics_exporter.time_series.failed", None),
+            2
+        );
+        assert_eq!(
+            u64_sum(&self_metrics, "gcp_metrics_exporter.time_series.dropped", None),
+            2
+        );
+        assert_eq!(
+            u64_sum(
+                &self_metrics,
+                "gcp_metrics_exporter.metric_descriptor.create",
+                Some(("outcome", "created"))
+            ),
+            1
+        );
+        assert_eq!(
+            histogram_count(&self_metrics, "gcp_metrics_exporter.export.duration"),
+            2
+        );
+    }
+}
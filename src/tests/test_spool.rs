@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{ExportStatsHandle, GCPMetricsExporterConfig, SpoolConfig};
+
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::runtime;
+    use opentelemetry_sdk::{
+        Resource,
+        metrics::{SdkMeterProvider, periodic_reader_with_async_runtime::PeriodicReader},
+    };
+    use pretty_assertions_sorted_fork::assert_eq;
+    use tempfile::TempDir;
+
+    fn init_metrics_with_spool(
+        mock_service: MockMetricService,
+        spool: SpoolConfig,
+    ) -> (SdkMeterProvider, ExportStatsHandle) {
+        let exporter = init_metrics_exporter_with_config(
+            mock_service,
+            GCPMetricsExporterConfig {
+                spool: Some(spool),
+                ..Default::default()
+            },
+        );
+        let stats = exporter.stats_handle();
+        let reader = PeriodicReader::builder(exporter, runtime::Tokio).build();
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_resource(
+                Resource::builder_empty()
+                    .with_attributes(vec![KeyValue::new("service.name", "metric-demo")])
+                    .build(),
+            )
+            .with_reader(reader)
+            .build();
+        (metrics_provider, stats)
+    }
+
+    fn int64_values(req: &google_cloud_monitoring_v3::model::CreateTimeSeriesRequest) -> Vec<i64> {
+        req.time_series
+            .iter()
+            .flat_map(|time_series| time_series.points.iter())
+            .map(|point| point.value.as_ref().unwrap().int64_value().cloned().unwrap())
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_spool_replays_failed_batches_in_order() {
+        let directory = TempDir::new().unwrap();
+        let mock_service = MockMetricService::new();
+        let (metrics_provider, stats) =
+            init_metrics_with_spool(mock_service.clone(), SpoolConfig::new(directory.path()));
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+
+        mock_service
+            .set_create_time_series_error(Some(google_cloud_gax::error::rpc::Code::Unavailable))
+            .await;
+        mycounter.add(1, &[]);
+        metrics_provider.force_flush().unwrap();
+        mycounter.add(2, &[]);
+        metrics_provider.force_flush().unwrap();
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 2);
+        assert_eq!(stats.stats().queue_depth, 2);
+
+        mock_service.set_create_time_series_error(None).await;
+        mycounter.add(3, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let create_time_series = mock_service.expect_create_time_series().await;
+        assert_eq!(
+            create_time_series.iter().map(int64_values).collect::<Vec<_>>(),
+            vec![vec![1], vec![3], vec![6]]
+        );
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 0);
+        assert_eq!(stats.stats().queue_depth, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_spool_ignores_non_retryable_errors() {
+        let directory = TempDir::new().unwrap();
+        let mock_service = MockMetricService::new();
+        let (metrics_provider, _) = init_metrics_with_spool(mock_service.clone(), SpoolConfig::new(directory.path()));
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+
+        mock_service
+            .set_create_time_series_error(Some(google_cloud_gax::error::rpc::Code::InvalidArgument))
+            .await;
+        mycounter.add(1, &[]);
+        assert!(metrics_provider.force_flush().is_err());
+
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{ExportOutcome, GCPMetricsExporterConfig};
+
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::runtime;
+    use opentelemetry_sdk::{
+        Resource,
+        metrics::{SdkMeterProvider, periodic_reader_with_async_runtime::PeriodicReader},
+    };
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_stats() {
+        let mock_service = MockMetricService::new();
+        let exporter = init_metrics_exporter_with_config(mock_service.clone(), GCPMetricsExporterConfig::default());
+        let stats = exporter.stats_handle();
+        assert_eq!(stats.stats(), Default::default());
+
+        let metrics_provider = SdkMeterProvider::builder()
+            .with_resource(
+                Resource::builder_empty()
+                    .with_attributes(vec![KeyValue::new("service.name", "metric-demo")])
+                    .build(),
+            )
+            .with_reader(PeriodicReader::builder(exporter, runtime::Tokio).build())
+            .build();
+        let meter = metrics_provider.meter("test_cloud_monitoring");
+        let mycounter = meter.u64_counter("mycounter").build();
+        let mygauge = meter.f64_gauge("mygauge").build();
+
+        mycounter.add(1, &[]);
+        mygauge.record(1.0, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let after_success = stats.stats();
+        assert_eq!(after_success.last_export_outcome, Some(ExportOutcome::Success));
+        assert!(after_success.last_export_time.is_some());
+        assert_eq!(after_success.last_success_time, after_success.last_export_time);
+        assert_eq!(after_success.time_series_sent, 2);
+        assert_eq!(after_success.time_series_failed, 0);
+        assert_eq!(after_success.cached_metric_descriptors, 2);
+        assert_eq!(after_success.last_time_series_error, None);
+
+        mock_service
+            .set_create_time_series_error(Some(google_cloud_gax::error::rpc::Code::Unavailable))
+            .await;
+        mycounter.add(1, &[]);
+        assert!(metrics_provider.force_flush().is_err());
+
+        let after_failure = stats.stats();
+        assert_eq!(after_failure.last_export_outcome, Some(ExportOutcome::Failure));
+        assert_eq!(after_failure.last_success_time, after_success.last_success_time);
+        assert_eq!(after_failure.time_series_sent, 2);
+        assert_eq!(after_failure.time_series_failed, 2);
+        assert!(after_failure.last_time_series_error.is_some());
+        assert_eq!(after_failure.last_metric_descriptor_error, None);
+        assert_eq!(after_failure.queue_depth, 0);
+    }
+}
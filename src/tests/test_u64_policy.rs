@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_utils::*;
+    use crate::{GCPMetricsExporterConfig, U64Policy};
+
+    use google_cloud_api::model::metric_descriptor::ValueType;
+    use google_cloud_monitoring_v3::model::TypedValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use pretty_assertions_sorted_fork::assert_eq;
+
+    const BIG: u64 = u64::MAX - 1;
+
+    /// Value type of the descriptor and value of the point of a u64 counter at `BIG`.
+    async fn export_big_counter(u64_policy: U64Policy) -> (ValueType, TypedValue) {
+        let mock_service = MockMetricService::new();
+        let (metrics_provider, _) = init_metrics_with_config(
+            mock_service.clone(),
+            GCPMetricsExporterConfig {
+                u64_policy,
+                ..Default::default()
+            },
+            SdkMeterProvider::builder(),
+        );
+        let meter = metrics_provider.meter("test_u64_policy");
+        meter.u64_counter("io.bytes").build().add(BIG, &[]);
+        metrics_provider.force_flush().unwrap();
+
+        let descriptors = mock_service.expect_create_metric_descriptor().await;
+        let value_type = descriptors[0].metric_descriptor.as_ref().unwrap().value_type.clone();
+        let time_series = mock_service.expect_create_time_series().await[0].time_series[0].clone();
+        assert_eq!(time_series.value_type, value_type);
+        (value_type, time_series.points[0].value.clone().unwrap())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_u64_policy_saturate() {
+        let (value_type, value) = export_big_counter(U64Policy::Saturate).await;
+        assert_eq!(value_type, ValueType::Int64);
+        assert_eq!(value, TypedValue::new().set_int64_value(i64::MAX));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_u64_policy_double() {
+        let (value_type, value) = export_big_counter(U64Policy::Double).await;
+        assert_eq!(value_type, ValueType::Double);
+        assert_eq!(value, TypedValue::new().set_double_value(BIG as f64));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_u64_policy_wrap() {
+        let (value_type, value) = export_big_counter(U64Policy::Wrap { modulo: 1 << 32 }).await;
+        assert_eq!(value_type, ValueType::Int64);
+        assert_eq!(value, TypedValue::new().set_int64_value((BIG % (1 << 32)) as i64));
+    }
+}
@@ -1,8 +1,16 @@
-cargo +1.85.0 test
-cargo +1.86.0 test
-cargo +1.87.0 test
-cargo +1.88.0 test
-cargo +1.89.0 test
-cargo +1.90.0 test
-cargo +1.91.1 test
-cargo +1.92.0 test
+cargo +1.85.0 test --all-features
+cargo +1.86.0 test --all-features
+cargo +1.87.0 test --all-features
+cargo +1.88.0 test --all-features
+cargo +1.89.0 test --all-features
+cargo +1.90.0 test --all-features
+cargo +1.91.1 test --all-features
+cargo +1.92.0 test --all-features
+# feature combinations
+cargo test --no-default-features
+cargo test
+cargo test --features spool
+cargo test --features test-utils
+cargo test --features cli
+cargo test --features schema-yaml,schema-toml
+cargo test --all-features